@@ -0,0 +1,752 @@
+//! Binary caches of parsed dictionaries, which load much faster than the
+//! XML they were parsed from.
+//!
+//! A cache starts with a header of the magic bytes `JMDC`, the format
+//! version, the kind of dictionary, the [`Source`] it was built from and the
+//! languages it was filtered to, followed by the dictionary itself. Integers are LEB128 varints, strings
+//! and lists are prefixed with their length and entity values are stored as
+//! their codes. A cache is rejected if any part of the header does not
+//! match, so it is rebuilt whenever this format or the source file changes.
+
+use crate::entities::{Dialect, Field, KanjiInfo, Misc, PartOfSpeech, ReadingInfo};
+use crate::errors::CacheError;
+use crate::jmdict::{self, Gloss, JMDict, Kanji, LSource, PriRef, Sense, XRef};
+use crate::kanjidic::{
+    self, Codepoint, DicRef, Grade, Kanjidic, Meaning, OnyomiType, Radical, RadicalType,
+    ReadingMeaning, ReadingType,
+};
+use crate::language::Language;
+use crate::tatoeba::{Sentence, Tatoeba};
+use std::convert::TryInto;
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::str;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::UNIX_EPOCH;
+
+const MAGIC: &[u8; 4] = b"JMDC";
+
+/// Bumped whenever the encoding of any dictionary type changes.
+pub const FORMAT_VERSION: u32 = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    JMDict = 0,
+    Kanjidic = 1,
+    Tatoeba = 2,
+}
+
+/// Identifies the version of a source file a cache was built from by its
+/// length, modification date and the version the dictionary declares.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Source {
+    pub len: u64,
+    // Seconds since the Unix epoch.
+    pub modified: u64,
+    // The creation date of JMdict and JMnedict, or the database version of
+    // Kanjidic. Tatoeba files do not declare one.
+    pub version: Option<String>,
+}
+
+impl Source {
+    pub fn of<P: AsRef<Path>>(path: P) -> Result<Self, CacheError> {
+        let path = path.as_ref();
+        let metadata = fs::metadata(path)?;
+        let modified = metadata
+            .modified()?
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs());
+
+        Ok(Source {
+            len: metadata.len(),
+            modified,
+            version: declared_version(path)?,
+        })
+    }
+}
+
+// Reads the version from the start of a dictionary file: the
+// `<!-- JMdict created: ... -->` comment or Kanjidic's `<database_version>`.
+// Stops at the first entry, or straight away for files that are not XML.
+fn declared_version(path: &Path) -> Result<Option<String>, CacheError> {
+    let mut reader = BufReader::new(File::open(path)?);
+    let mut line = Vec::new();
+    let mut first = true;
+
+    while reader.read_until(b'\n', &mut line)? > 0 {
+        let text = String::from_utf8_lossy(&line);
+        let text = text.trim();
+        if first && !text.is_empty() && !text.starts_with('<') {
+            break;
+        }
+        first &= text.is_empty();
+
+        let version = match text.strip_prefix("<!--") {
+            Some(comment) => comment
+                .split_once("created:")
+                .and_then(|(_, rest)| rest.split_once("-->")),
+            None => text
+                .split_once("<database_version>")
+                .and_then(|(_, rest)| rest.split_once("</database_version>")),
+        };
+        if let Some((version, _)) = version {
+            return Ok(Some(version.trim().to_owned()));
+        }
+        if text.starts_with("<entry") || text.starts_with("<character") {
+            break;
+        }
+        line.clear();
+    }
+    Ok(None)
+}
+
+/// Reads the source recorded in the header of a cache.
+pub fn read_source<P: AsRef<Path>>(path: P) -> Result<Source, CacheError> {
+    let bytes = fs::read(path)?;
+    let mut input = Decoder { bytes: &bytes };
+    read_header(&mut input).map(|(_, source, _)| source)
+}
+
+impl JMDict {
+    /// Writes the dictionary to a cache at `path`, recording the XML file it
+    /// was parsed from.
+    pub fn save_cache<P: AsRef<Path>>(&self, path: P, source: &Source) -> Result<(), CacheError> {
+        save(path.as_ref(), Kind::JMDict, source, &None, self)
+    }
+
+    /// Loads a cache written by [`JMDict::save_cache`] from the same
+    /// `source`.
+    pub fn load_cache<P: AsRef<Path>>(path: P, source: &Source) -> Result<Self, CacheError> {
+        load(path.as_ref(), Kind::JMDict, source, &None)
+    }
+
+    /// Loads the cache at `cache_path` if it was built from the current
+    /// version of `filepath`, and otherwise parses the XML and rewrites the
+    /// cache.
+    pub fn from_file_cached<P: AsRef<Path>>(
+        filepath: &str,
+        cache_path: P,
+    ) -> Result<Self, CacheError> {
+        let source = Source::of(filepath)?;
+        if let Ok(dict) = JMDict::load_cache(&cache_path, &source) {
+            return Ok(dict);
+        }
+
+        let dict = JMDict::from_file(filepath)?;
+        dict.save_cache(&cache_path, &source)?;
+        Ok(dict)
+    }
+}
+
+impl Kanjidic {
+    pub fn save_cache<P: AsRef<Path>>(&self, path: P, source: &Source) -> Result<(), CacheError> {
+        save(path.as_ref(), Kind::Kanjidic, source, &None, self)
+    }
+
+    pub fn load_cache<P: AsRef<Path>>(path: P, source: &Source) -> Result<Self, CacheError> {
+        load(path.as_ref(), Kind::Kanjidic, source, &None)
+    }
+
+    pub fn from_file_cached<P: AsRef<Path>>(
+        filepath: &str,
+        cache_path: P,
+    ) -> Result<Self, CacheError> {
+        let source = Source::of(filepath)?;
+        if let Ok(dict) = Kanjidic::load_cache(&cache_path, &source) {
+            return Ok(dict);
+        }
+
+        let dict = Kanjidic::from_file(filepath)?;
+        dict.save_cache(&cache_path, &source)?;
+        Ok(dict)
+    }
+}
+
+impl Tatoeba {
+    /// Writes the sentences to a cache at `path`, recording the languages
+    /// they were filtered to, or `None` if they were not filtered.
+    pub fn save_cache<P: AsRef<Path>>(
+        &self,
+        path: P,
+        source: &Source,
+        languages: Option<&[Language]>,
+    ) -> Result<(), CacheError> {
+        let languages = language_key(languages);
+        save(path.as_ref(), Kind::Tatoeba, source, &languages, self)
+    }
+
+    /// Loads a cache written by [`Tatoeba::save_cache`] from the same
+    /// `source` and filtered to the same languages, in any order.
+    pub fn load_cache<P: AsRef<Path>>(
+        path: P,
+        source: &Source,
+        languages: Option<&[Language]>,
+    ) -> Result<Self, CacheError> {
+        let languages = language_key(languages);
+        load(path.as_ref(), Kind::Tatoeba, source, &languages)
+    }
+
+    /// Like [`JMDict::from_file_cached`], keeping only the sentences in
+    /// `languages` if given. A cache filtered to other languages is rebuilt.
+    pub fn from_file_cached<P: AsRef<Path>>(
+        filepath: &str,
+        cache_path: P,
+        languages: Option<&[Language]>,
+    ) -> Result<Self, CacheError> {
+        let source = Source::of(filepath)?;
+        if let Ok(tatoeba) = Tatoeba::load_cache(&cache_path, &source, languages) {
+            return Ok(tatoeba);
+        }
+
+        let filter = languages
+            .map(|languages| move |code: &str| languages.iter().any(|l| l.iso639_3() == code));
+        let tatoeba = Tatoeba::from_file(filepath, filter)?;
+        tatoeba.save_cache(&cache_path, &source, languages)?;
+        Ok(tatoeba)
+    }
+}
+
+// Sorted and without duplicates, so that the same languages in another
+// order match.
+fn language_key(languages: Option<&[Language]>) -> Option<Vec<Language>> {
+    languages.map(|languages| {
+        let mut languages = languages.to_vec();
+        languages.sort();
+        languages.dedup();
+        languages
+    })
+}
+
+fn save<T: Encode>(
+    path: &Path,
+    kind: Kind,
+    source: &Source,
+    languages: &Option<Vec<Language>>,
+    body: &T,
+) -> Result<(), CacheError> {
+    let mut out = MAGIC.to_vec();
+    FORMAT_VERSION.encode(&mut out);
+    out.push(kind as u8);
+    source.len.encode(&mut out);
+    source.modified.encode(&mut out);
+    source.version.encode(&mut out);
+    languages.encode(&mut out);
+    body.encode(&mut out);
+
+    write_replacing(path, &out)
+}
+
+// Writes to a temporary file first so that an interrupted write never
+// leaves a truncated file behind, and so that processes with the old file
+// open or mapped keep reading it unchanged. The temporary name is unique to
+// the process and call, so concurrent writers never share one.
+pub(crate) fn write_replacing(path: &Path, bytes: &[u8]) -> Result<(), CacheError> {
+    let temporary = temporary_path(path);
+    let written = fs::write(&temporary, bytes).and_then(|_| fs::rename(&temporary, path));
+    if written.is_err() {
+        let _ = fs::remove_file(&temporary);
+    }
+    Ok(written?)
+}
+
+fn temporary_path(path: &Path) -> PathBuf {
+    static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+    let mut name = path.file_name().unwrap_or_default().to_owned();
+    name.push(format!(
+        ".{}.{}.tmp",
+        process::id(),
+        COUNTER.fetch_add(1, Ordering::Relaxed)
+    ));
+    path.with_file_name(name)
+}
+
+fn load<T: Encode>(
+    path: &Path,
+    kind: Kind,
+    source: &Source,
+    languages: &Option<Vec<Language>>,
+) -> Result<T, CacheError> {
+    let bytes = fs::read(path)?;
+    let mut input = Decoder { bytes: &bytes };
+
+    let (cached_kind, cached_source, cached_languages) = read_header(&mut input)?;
+    if cached_kind != kind as u8 {
+        return Err(CacheError::InvalidHeader);
+    }
+    if cached_source != *source {
+        return Err(CacheError::SourceChanged);
+    }
+    if cached_languages != *languages {
+        return Err(CacheError::LanguagesChanged);
+    }
+
+    let body = T::decode(&mut input)?;
+    if !input.bytes.is_empty() {
+        return Err(CacheError::Corrupt);
+    }
+    Ok(body)
+}
+
+fn read_header(input: &mut Decoder) -> Result<(u8, Source, Option<Vec<Language>>), CacheError> {
+    if !input.bytes.starts_with(MAGIC) {
+        return Err(CacheError::InvalidHeader);
+    }
+    input.take(MAGIC.len())?;
+
+    let version = u32::decode(input)?;
+    if version != FORMAT_VERSION {
+        return Err(CacheError::FormatVersion(version));
+    }
+
+    let kind = input.byte()?;
+    let source = Source {
+        len: u64::decode(input)?,
+        modified: u64::decode(input)?,
+        version: Option::decode(input)?,
+    };
+    let languages = Option::decode(input)?;
+    Ok((kind, source, languages))
+}
+
+struct Decoder<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Decoder<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], CacheError> {
+        if len > self.bytes.len() {
+            return Err(CacheError::Corrupt);
+        }
+        let (taken, rest) = self.bytes.split_at(len);
+        self.bytes = rest;
+        Ok(taken)
+    }
+
+    fn byte(&mut self) -> Result<u8, CacheError> {
+        self.take(1).map(|b| b[0])
+    }
+}
+
+trait Encode: Sized {
+    fn encode(&self, out: &mut Vec<u8>);
+    fn decode(input: &mut Decoder) -> Result<Self, CacheError>;
+}
+
+impl Encode for u64 {
+    fn encode(&self, out: &mut Vec<u8>) {
+        let mut n = *self;
+        while n >= 0x80 {
+            out.push(n as u8 | 0x80);
+            n >>= 7;
+        }
+        out.push(n as u8);
+    }
+
+    fn decode(input: &mut Decoder) -> Result<Self, CacheError> {
+        let mut n = 0u64;
+        for shift in (0..64).step_by(7) {
+            let b = input.byte()?;
+            n |= u64::from(b & 0x7f) << shift;
+            if b & 0x80 == 0 {
+                return Ok(n);
+            }
+        }
+        Err(CacheError::Corrupt)
+    }
+}
+
+impl Encode for u32 {
+    fn encode(&self, out: &mut Vec<u8>) {
+        u64::from(*self).encode(out)
+    }
+
+    fn decode(input: &mut Decoder) -> Result<Self, CacheError> {
+        u64::decode(input)?
+            .try_into()
+            .map_err(|_| CacheError::Corrupt)
+    }
+}
+
+impl Encode for usize {
+    fn encode(&self, out: &mut Vec<u8>) {
+        (*self as u64).encode(out)
+    }
+
+    fn decode(input: &mut Decoder) -> Result<Self, CacheError> {
+        u64::decode(input)?
+            .try_into()
+            .map_err(|_| CacheError::Corrupt)
+    }
+}
+
+impl Encode for bool {
+    fn encode(&self, out: &mut Vec<u8>) {
+        out.push(*self as u8)
+    }
+
+    fn decode(input: &mut Decoder) -> Result<Self, CacheError> {
+        match input.byte()? {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err(CacheError::Corrupt),
+        }
+    }
+}
+
+impl Encode for String {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.len().encode(out);
+        out.extend_from_slice(self.as_bytes());
+    }
+
+    fn decode(input: &mut Decoder) -> Result<Self, CacheError> {
+        let len = usize::decode(input)?;
+        let bytes = input.take(len)?;
+        str::from_utf8(bytes)
+            .map(str::to_owned)
+            .map_err(|_| CacheError::Corrupt)
+    }
+}
+
+impl<T: Encode> Encode for Option<T> {
+    fn encode(&self, out: &mut Vec<u8>) {
+        match self {
+            Some(value) => {
+                out.push(1);
+                value.encode(out);
+            }
+            None => out.push(0),
+        }
+    }
+
+    fn decode(input: &mut Decoder) -> Result<Self, CacheError> {
+        match input.byte()? {
+            0 => Ok(None),
+            1 => Ok(Some(T::decode(input)?)),
+            _ => Err(CacheError::Corrupt),
+        }
+    }
+}
+
+impl<T: Encode> Encode for Vec<T> {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.len().encode(out);
+        for item in self {
+            item.encode(out);
+        }
+    }
+
+    fn decode(input: &mut Decoder) -> Result<Self, CacheError> {
+        let len = usize::decode(input)?;
+        // Every item takes at least one byte, which bounds the allocation
+        // for a corrupt length.
+        let mut items = Vec::with_capacity(len.min(input.bytes.len()));
+        for _ in 0..len {
+            items.push(T::decode(input)?);
+        }
+        Ok(items)
+    }
+}
+
+impl Encode for Language {
+    fn encode(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(self.iso639_3().as_bytes())
+    }
+
+    fn decode(input: &mut Decoder) -> Result<Self, CacheError> {
+        let code = str::from_utf8(input.take(3)?).map_err(|_| CacheError::Corrupt)?;
+        Language::from_iso639_3(code).map_err(|_| CacheError::Corrupt)
+    }
+}
+
+macro_rules! encode_entities {
+    ($($name:ident),*) => {
+        $(
+            impl Encode for $name {
+                fn encode(&self, out: &mut Vec<u8>) {
+                    self.code().to_owned().encode(out)
+                }
+
+                fn decode(input: &mut Decoder) -> Result<Self, CacheError> {
+                    Ok($name::from_code(&String::decode(input)?))
+                }
+            }
+        )*
+    };
+}
+
+encode_entities!(Dialect, Field, KanjiInfo, Misc, PartOfSpeech, ReadingInfo);
+
+macro_rules! encode_struct {
+    ($name:path { $($field:ident),* $(,)? }) => {
+        impl Encode for $name {
+            fn encode(&self, out: &mut Vec<u8>) {
+                $( self.$field.encode(out); )*
+            }
+
+            fn decode(input: &mut Decoder) -> Result<Self, CacheError> {
+                Ok(Self {
+                    $( $field: Encode::decode(input)?, )*
+                })
+            }
+        }
+    };
+}
+
+encode_struct!(JMDict { entries });
+encode_struct!(jmdict::Entry {
+    seq,
+    reading,
+    kanji,
+    sense
+});
+encode_struct!(Kanji {
+    text,
+    info,
+    pri_ref
+});
+encode_struct!(jmdict::Reading {
+    text,
+    no_kanji,
+    pri_ref,
+    restrict,
+    info
+});
+encode_struct!(XRef {
+    kanji,
+    reading,
+    sense_index
+});
+encode_struct!(Gloss {
+    content,
+    lang,
+    gender,
+    typ
+});
+encode_struct!(LSource {
+    content,
+    lang,
+    full,
+    wasei
+});
+encode_struct!(Sense {
+    restrict_reading,
+    restrict_kanji,
+    cross_refs,
+    gloss,
+    antonyms,
+    pos,
+    fields,
+    misc,
+    source_lang,
+    dialects,
+    info,
+});
+
+encode_struct!(Kanjidic {
+    file_version,
+    database_version,
+    creation_date,
+    entries,
+});
+encode_struct!(kanjidic::Entry {
+    literal,
+    codepoints,
+    reading_meanings,
+    nanori_readings,
+    radicals,
+    stroke_count,
+    stroke_miscounts,
+    grade,
+    freq,
+    old_jlpt,
+    dic_refs,
+});
+encode_struct!(Codepoint { standard, value });
+encode_struct!(ReadingMeaning { readings, meanings });
+encode_struct!(kanjidic::Reading { value, typ });
+encode_struct!(Meaning { content, language });
+encode_struct!(Radical {
+    classification,
+    value
+});
+
+encode_struct!(Tatoeba { entries });
+encode_struct!(Sentence { content, language });
+
+impl Encode for PriRef {
+    fn encode(&self, out: &mut Vec<u8>) {
+        match self {
+            PriRef::News1 => out.push(0),
+            PriRef::News2 => out.push(1),
+            PriRef::Ichi1 => out.push(2),
+            PriRef::Ichi2 => out.push(3),
+            PriRef::Spec1 => out.push(4),
+            PriRef::Spec2 => out.push(5),
+            PriRef::Gai1 => out.push(6),
+            PriRef::Gai2 => out.push(7),
+            PriRef::NF(n) => {
+                out.push(8);
+                n.encode(out);
+            }
+        }
+    }
+
+    fn decode(input: &mut Decoder) -> Result<Self, CacheError> {
+        Ok(match input.byte()? {
+            0 => PriRef::News1,
+            1 => PriRef::News2,
+            2 => PriRef::Ichi1,
+            3 => PriRef::Ichi2,
+            4 => PriRef::Spec1,
+            5 => PriRef::Spec2,
+            6 => PriRef::Gai1,
+            7 => PriRef::Gai2,
+            8 => PriRef::NF(u32::decode(input)?),
+            _ => return Err(CacheError::Corrupt),
+        })
+    }
+}
+
+impl Encode for ReadingType {
+    fn encode(&self, out: &mut Vec<u8>) {
+        match self {
+            ReadingType::Pinyin => out.push(0),
+            ReadingType::KoreanR => out.push(1),
+            ReadingType::KoreanH => out.push(2),
+            ReadingType::Vietnam => out.push(3),
+            ReadingType::Onyomi(jouyou, typ) => {
+                out.push(4);
+                jouyou.encode(out);
+                typ.encode(out);
+            }
+            ReadingType::Kunyomi(jouyou) => {
+                out.push(5);
+                jouyou.encode(out);
+            }
+        }
+    }
+
+    fn decode(input: &mut Decoder) -> Result<Self, CacheError> {
+        Ok(match input.byte()? {
+            0 => ReadingType::Pinyin,
+            1 => ReadingType::KoreanR,
+            2 => ReadingType::KoreanH,
+            3 => ReadingType::Vietnam,
+            4 => ReadingType::Onyomi(bool::decode(input)?, OnyomiType::decode(input)?),
+            5 => ReadingType::Kunyomi(bool::decode(input)?),
+            _ => return Err(CacheError::Corrupt),
+        })
+    }
+}
+
+impl Encode for OnyomiType {
+    fn encode(&self, out: &mut Vec<u8>) {
+        out.push(match self {
+            OnyomiType::Kan => 0,
+            OnyomiType::Go => 1,
+            OnyomiType::Tou => 2,
+            OnyomiType::Kanyou => 3,
+            OnyomiType::None => 4,
+        })
+    }
+
+    fn decode(input: &mut Decoder) -> Result<Self, CacheError> {
+        Ok(match input.byte()? {
+            0 => OnyomiType::Kan,
+            1 => OnyomiType::Go,
+            2 => OnyomiType::Tou,
+            3 => OnyomiType::Kanyou,
+            4 => OnyomiType::None,
+            _ => return Err(CacheError::Corrupt),
+        })
+    }
+}
+
+impl Encode for RadicalType {
+    fn encode(&self, out: &mut Vec<u8>) {
+        out.push(match self {
+            RadicalType::Classical => 0,
+            RadicalType::NelsonC => 1,
+        })
+    }
+
+    fn decode(input: &mut Decoder) -> Result<Self, CacheError> {
+        Ok(match input.byte()? {
+            0 => RadicalType::Classical,
+            1 => RadicalType::NelsonC,
+            _ => return Err(CacheError::Corrupt),
+        })
+    }
+}
+
+impl Encode for Grade {
+    fn encode(&self, out: &mut Vec<u8>) {
+        match self {
+            Grade::Kyouiku(grade) => {
+                out.push(0);
+                grade.encode(out);
+            }
+            Grade::Jouyou => out.push(1),
+            Grade::Jinmeiyou => out.push(2),
+            Grade::JouyouVariant => out.push(3),
+        }
+    }
+
+    fn decode(input: &mut Decoder) -> Result<Self, CacheError> {
+        Ok(match input.byte()? {
+            0 => Grade::Kyouiku(u32::decode(input)?),
+            1 => Grade::Jouyou,
+            2 => Grade::Jinmeiyou,
+            3 => Grade::JouyouVariant,
+            _ => return Err(CacheError::Corrupt),
+        })
+    }
+}
+
+macro_rules! encode_dic_refs {
+    ($($tag:literal => $variant:ident,)*) => {
+        impl Encode for DicRef {
+            fn encode(&self, out: &mut Vec<u8>) {
+                match self {
+                    $(
+                        DicRef::$variant(index) => {
+                            out.push($tag);
+                            index.encode(out);
+                        }
+                    )*
+                    DicRef::Moro(index, volume, page) => {
+                        out.push(255);
+                        index.encode(out);
+                        volume.encode(out);
+                        page.encode(out);
+                    }
+                }
+            }
+
+            fn decode(input: &mut Decoder) -> Result<Self, CacheError> {
+                Ok(match input.byte()? {
+                    $( $tag => DicRef::$variant(String::decode(input)?), )*
+                    255 => DicRef::Moro(
+                        String::decode(input)?,
+                        Option::decode(input)?,
+                        Option::decode(input)?,
+                    ),
+                    _ => return Err(CacheError::Corrupt),
+                })
+            }
+        }
+    };
+}
+
+#[rustfmt::skip]
+encode_dic_refs! {
+    0 => NelsonC, 1 => NelsonN, 2 => HalpernNJECD, 3 => HalpernKKD, 4 => HalpernKKLD,
+    5 => HalpernKKLD2, 6 => Heisig, 7 => Heisig6, 8 => Gakken, 9 => OneillNames,
+    10 => OneillKK, 11 => NeillKK, 12 => Henshall, 13 => SHKK, 14 => SHKK2,
+    15 => Sakade, 16 => JFCards, 17 => Henshall3, 18 => TuttCards, 19 => Crowley,
+    20 => InContext, 21 => BusyPeople, 22 => KodanshaCompact, 23 => Maniette,
+}
@@ -0,0 +1,354 @@
+//! Conjugation tables for verbs and i-adjectives, generated from the part
+//! of speech of their senses.
+
+use crate::deinflect::GODAN;
+use crate::entities::PartOfSpeech;
+use crate::jmdict::{Entry, Form, Kanji, Reading};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Conjugation {
+    NonPast,
+    NonPastPolite,
+    Negative,
+    NegativePolite,
+    Past,
+    PastPolite,
+    NegativePast,
+    NegativePastPolite,
+    Te,
+    NegativeTe,
+    Potential,
+    Passive,
+    Causative,
+    CausativePassive,
+    Volitional,
+    VolitionalPolite,
+    Imperative,
+    NegativeImperative,
+    Conditional,
+    NegativeConditional,
+    Tara,
+    Desire,
+    // く form of adjectives, e.g. 高く.
+    Adverbial,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConjugatedForm {
+    pub conjugation: Conjugation,
+    pub written: String,
+    pub reading: String,
+}
+
+/// Every conjugation of one written form of an entry, for one of the parts
+/// of speech of its senses.
+#[derive(Debug)]
+pub struct ConjugationTable<'a> {
+    pub kanji: Option<&'a Kanji>,
+    pub reading: &'a Reading,
+    pub pos: PartOfSpeech,
+    pub forms: Vec<ConjugatedForm>,
+}
+
+impl Entry {
+    /// Conjugation tables for each written form, see [`Form::conjugations`].
+    pub fn conjugations(&self) -> Vec<ConjugationTable<'_>> {
+        self.forms().iter().flat_map(Form::conjugations).collect()
+    }
+}
+
+impl<'a> Form<'a> {
+    /// One table for every conjugable part of speech among the senses of
+    /// the form. Nouns taking する ("vs") are conjugated with it appended.
+    pub fn conjugations(&self) -> Vec<ConjugationTable<'a>> {
+        let mut pos: Vec<&PartOfSpeech> = Vec::new();
+        for p in self.senses.iter().flat_map(|s| &s.pos) {
+            if !pos.contains(&p) {
+                pos.push(p);
+            }
+        }
+
+        let written = self.kanji.map_or(&self.reading.text, |k| &k.text);
+        pos.into_iter()
+            .filter_map(|p| {
+                let written = conjugate(written, p)?;
+                let reading = conjugate(&self.reading.text, p)?;
+                let forms = written
+                    .into_iter()
+                    .zip(reading)
+                    .map(|((conjugation, written), (_, reading))| ConjugatedForm {
+                        conjugation,
+                        written,
+                        reading,
+                    })
+                    .collect();
+
+                Some(ConjugationTable {
+                    kanji: self.kanji,
+                    reading: self.reading,
+                    pos: p.clone(),
+                    forms,
+                })
+            })
+            .collect()
+    }
+}
+
+impl Reading {
+    pub fn conjugate(&self, pos: &PartOfSpeech) -> Option<Vec<(Conjugation, String)>> {
+        conjugate(&self.text, pos)
+    }
+}
+
+/// Conjugates a word in dictionary form as the given part of speech, or
+/// returns `None` if the part of speech does not conjugate or the word does
+/// not have the expected ending.
+pub fn conjugate(word: &str, pos: &PartOfSpeech) -> Option<Vec<(Conjugation, String)>> {
+    use PartOfSpeech::*;
+
+    match pos {
+        Ichidan | IchidanKureru => {
+            let stem = word.strip_suffix('る')?;
+            let mut stems = Stems::ichidan(word, stem);
+            if *pos == IchidanKureru {
+                stems.imperative = stem.to_owned();
+            }
+            Some(stems.verb_forms())
+        }
+        GodanAru | GodanBu | GodanGu | GodanKu | GodanIku | GodanMu | GodanNu | GodanRu
+        | GodanRuIrregular | GodanSu | GodanTsu | GodanU | GodanUSpecial => {
+            godan(word, pos).map(|s| s.verb_forms())
+        }
+        Suru => Some(suru(&format!("{}する", word)).verb_forms()),
+        SuruSpecial => {
+            let stem = word.strip_suffix("する")?;
+            Some(suru_special(word, stem).verb_forms())
+        }
+        SuruIncluded => {
+            word.strip_suffix("する")?;
+            Some(suru(word).verb_forms())
+        }
+        Kuru => kuru(word).map(|s| s.verb_forms()),
+        Adjective | AdjectiveYoiIi => adjective(word, *pos == AdjectiveYoiIi),
+        _ => None,
+    }
+}
+
+// The parts verb conjugations are built from.
+struct Stems {
+    dict: String,
+    // Base for ない, e.g. 書か.
+    negative: String,
+    // Base for ます, e.g. 書き.
+    masu: String,
+    te: String,
+    ta: String,
+    potential: String,
+    passive: String,
+    causative: String,
+    causative_passive: String,
+    volitional: String,
+    imperative: String,
+    conditional: String,
+}
+
+impl Stems {
+    fn ichidan(dict: &str, stem: &str) -> Self {
+        Stems {
+            dict: dict.to_owned(),
+            negative: stem.to_owned(),
+            masu: stem.to_owned(),
+            te: format!("{}て", stem),
+            ta: format!("{}た", stem),
+            potential: format!("{}られる", stem),
+            passive: format!("{}られる", stem),
+            causative: format!("{}させる", stem),
+            causative_passive: format!("{}させられる", stem),
+            volitional: format!("{}よう", stem),
+            imperative: format!("{}ろ", stem),
+            conditional: format!("{}れば", stem),
+        }
+    }
+
+    fn verb_forms(&self) -> Vec<(Conjugation, String)> {
+        let Stems {
+            dict,
+            negative,
+            masu,
+            ta,
+            ..
+        } = self;
+
+        vec![
+            (Conjugation::NonPast, dict.clone()),
+            (Conjugation::NonPastPolite, format!("{}ます", masu)),
+            (Conjugation::Negative, format!("{}ない", negative)),
+            (Conjugation::NegativePolite, format!("{}ません", masu)),
+            (Conjugation::Past, ta.clone()),
+            (Conjugation::PastPolite, format!("{}ました", masu)),
+            (Conjugation::NegativePast, format!("{}なかった", negative)),
+            (
+                Conjugation::NegativePastPolite,
+                format!("{}ませんでした", masu),
+            ),
+            (Conjugation::Te, self.te.clone()),
+            (Conjugation::NegativeTe, format!("{}なくて", negative)),
+            (Conjugation::Potential, self.potential.clone()),
+            (Conjugation::Passive, self.passive.clone()),
+            (Conjugation::Causative, self.causative.clone()),
+            (
+                Conjugation::CausativePassive,
+                self.causative_passive.clone(),
+            ),
+            (Conjugation::Volitional, self.volitional.clone()),
+            (Conjugation::VolitionalPolite, format!("{}ましょう", masu)),
+            (Conjugation::Imperative, self.imperative.clone()),
+            (Conjugation::NegativeImperative, format!("{}な", dict)),
+            (Conjugation::Conditional, self.conditional.clone()),
+            (
+                Conjugation::NegativeConditional,
+                format!("{}なければ", negative),
+            ),
+            (Conjugation::Tara, format!("{}ら", ta)),
+            (Conjugation::Desire, format!("{}たい", masu)),
+        ]
+    }
+}
+
+fn godan(word: &str, pos: &PartOfSpeech) -> Option<Stems> {
+    let last = word.chars().last()?;
+    let stem = &word[..word.len() - last.len_utf8()];
+    let row = GODAN.iter().find(|row| row[0].starts_with(last))?;
+    let [_, a, i, e, o, mut te, mut ta] = *row;
+
+    let mut negative = format!("{}{}", stem, a);
+    let mut masu = format!("{}{}", stem, i);
+    let mut imperative = format!("{}{}", stem, e);
+
+    match pos {
+        // 行く geminates instead of taking い.
+        PartOfSpeech::GodanIku => {
+            te = "って";
+            ta = "った";
+        }
+        // 問う and 請う keep the う.
+        PartOfSpeech::GodanUSpecial => {
+            te = "うて";
+            ta = "うた";
+        }
+        // ある has no あらない; the negative is the adjective ない.
+        PartOfSpeech::GodanRuIrregular => {
+            let before = word.chars().count().checked_sub(2)?;
+            negative = word.chars().take(before).collect();
+        }
+        // なさる, くださる and the like use い where り is expected.
+        PartOfSpeech::GodanAru => {
+            masu = format!("{}い", stem);
+            imperative = masu.clone();
+        }
+        _ => {}
+    }
+
+    Some(Stems {
+        dict: word.to_owned(),
+        negative,
+        masu,
+        te: format!("{}{}", stem, te),
+        ta: format!("{}{}", stem, ta),
+        potential: format!("{}{}る", stem, e),
+        passive: format!("{}{}れる", stem, a),
+        causative: format!("{}{}せる", stem, a),
+        causative_passive: format!("{}{}せられる", stem, a),
+        volitional: format!("{}{}う", stem, o),
+        imperative,
+        conditional: format!("{}{}ば", stem, e),
+    })
+}
+
+fn suru(word: &str) -> Stems {
+    let stem = word.strip_suffix("する").unwrap_or(word);
+    Stems {
+        dict: word.to_owned(),
+        negative: format!("{}し", stem),
+        masu: format!("{}し", stem),
+        te: format!("{}して", stem),
+        ta: format!("{}した", stem),
+        potential: format!("{}できる", stem),
+        passive: format!("{}される", stem),
+        causative: format!("{}させる", stem),
+        causative_passive: format!("{}させられる", stem),
+        volitional: format!("{}しよう", stem),
+        imperative: format!("{}しろ", stem),
+        conditional: format!("{}すれば", stem),
+    }
+}
+
+// する verbs on a one-kanji stem, such as 愛する, take さ and せ where
+// other する verbs take し and でき: 愛さない, 愛せる, 愛せ.
+fn suru_special(word: &str, stem: &str) -> Stems {
+    Stems {
+        negative: format!("{}さ", stem),
+        potential: format!("{}せる", stem),
+        imperative: format!("{}せ", stem),
+        ..suru(word)
+    }
+}
+
+fn kuru(word: &str) -> Option<Stems> {
+    // Written with kanji, the stem 来 stays the same and only the okurigana
+    // change; in kana the stem vowel changes between く, き and こ.
+    let (prefix, ko, ki, ku) = match word.strip_suffix("来る") {
+        Some(prefix) => (prefix, "来", "来", "来"),
+        None => (word.strip_suffix("くる")?, "こ", "き", "く"),
+    };
+    let ko = format!("{}{}", prefix, ko);
+    let ki = format!("{}{}", prefix, ki);
+    let ku = format!("{}{}", prefix, ku);
+
+    Some(Stems {
+        dict: word.to_owned(),
+        negative: ko.clone(),
+        masu: ki.clone(),
+        te: format!("{}て", ki),
+        ta: format!("{}た", ki),
+        potential: format!("{}られる", ko),
+        passive: format!("{}られる", ko),
+        causative: format!("{}させる", ko),
+        causative_passive: format!("{}させられる", ko),
+        volitional: format!("{}よう", ko),
+        imperative: format!("{}い", ko),
+        conditional: format!("{}れば", ku),
+    })
+}
+
+fn adjective(word: &str, yoi_ii: bool) -> Option<Vec<(Conjugation, String)>> {
+    let stem = word.strip_suffix('い')?;
+    // いい conjugates from its older form よい.
+    let stem = match stem.strip_suffix('い') {
+        Some(prefix) if yoi_ii => format!("{}よ", prefix),
+        _ => stem.to_owned(),
+    };
+
+    Some(vec![
+        (Conjugation::NonPast, word.to_owned()),
+        (Conjugation::NonPastPolite, format!("{}です", word)),
+        (Conjugation::Negative, format!("{}くない", stem)),
+        (Conjugation::NegativePolite, format!("{}くないです", stem)),
+        (Conjugation::Past, format!("{}かった", stem)),
+        (Conjugation::PastPolite, format!("{}かったです", stem)),
+        (Conjugation::NegativePast, format!("{}くなかった", stem)),
+        (
+            Conjugation::NegativePastPolite,
+            format!("{}くなかったです", stem),
+        ),
+        (Conjugation::Te, format!("{}くて", stem)),
+        (Conjugation::NegativeTe, format!("{}くなくて", stem)),
+        (Conjugation::Conditional, format!("{}ければ", stem)),
+        (
+            Conjugation::NegativeConditional,
+            format!("{}くなければ", stem),
+        ),
+        (Conjugation::Tara, format!("{}かったら", stem)),
+        (Conjugation::Adverbial, format!("{}く", stem)),
+    ])
+}
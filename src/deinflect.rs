@@ -0,0 +1,371 @@
+//! Rule-based deinflection of conjugated verbs and adjectives.
+//!
+//! Each rule replaces an inflected ending with a less inflected one, e.g.
+//! "かった" with "い". Rules are applied repeatedly, so "食べなかった"
+//! becomes "食べない" and then "食べる". Every rule states which word class
+//! the shorter form belongs to and which classes the longer form may have,
+//! which keeps chains such as past → negative from being applied to words
+//! that cannot take them.
+
+use crate::entities::PartOfSpeech;
+use crate::jmdict::Entry;
+use crate::search;
+use std::collections::HashSet;
+
+/// The conjugation class of a deinflected form. Only the first five
+/// correspond to dictionary forms; the others are intermediate forms such
+/// as "食べます" or "食べて" that take further endings.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum WordClass {
+    Ichidan,
+    Godan,
+    Suru,
+    Kuru,
+    IAdjective,
+    // Polite forms ending in ます.
+    Masu,
+    // Te forms ending in て or で.
+    Te,
+}
+
+/// A grammatical step undone by a rule.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Reason {
+    Negative,
+    Past,
+    Te,
+    Polite,
+    Potential,
+    Passive,
+    // られる, which is both the potential and the passive of ichidan
+    // verbs and 来る.
+    PotentialOrPassive,
+    Causative,
+    Volitional,
+    Imperative,
+    Conditional,
+    Tara,
+    Tari,
+    Desire,
+    Progressive,
+    Completion,
+    Adverbial,
+    Noun,
+    Zu,
+}
+
+/// A candidate dictionary form of an inflected word.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Deinflection {
+    pub term: String,
+    // Steps that lead from `term` to the inflected word, e.g. negative then
+    // past for "食べなかった".
+    pub reasons: Vec<Reason>,
+    // `None` for the word itself, which may belong to any class.
+    pub class: Option<WordClass>,
+}
+
+#[derive(Debug, Clone)]
+struct Rule {
+    from: String,
+    to: String,
+    // Classes the inflected form may have. Empty for endings that nothing
+    // else attaches to, which therefore only match the original word.
+    input: &'static [WordClass],
+    output: WordClass,
+    reasons: &'static [Reason],
+}
+
+/// Holds the rule table so that it is built once for many lookups.
+#[derive(Debug, Clone)]
+pub struct Deinflector {
+    rules: Vec<Rule>,
+}
+
+impl Default for Deinflector {
+    fn default() -> Self {
+        Deinflector::new()
+    }
+}
+
+impl Deinflector {
+    pub fn new() -> Self {
+        Deinflector { rules: rules() }
+    }
+
+    /// Returns the word itself followed by every form it can be
+    /// deinflected to, shortest chains first. Candidates in intermediate
+    /// classes such as [`WordClass::Te`] are not included.
+    pub fn deinflect(&self, word: &str) -> Vec<Deinflection> {
+        let mut results = vec![Deinflection {
+            term: word.to_owned(),
+            reasons: Vec::new(),
+            class: None,
+        }];
+        let mut seen = HashSet::new();
+
+        let mut i = 0;
+        while i < results.len() {
+            let current = results[i].clone();
+            i += 1;
+
+            for rule in &self.rules {
+                let accepts = match current.class {
+                    Some(class) => rule.input.contains(&class),
+                    None => true,
+                };
+                if !accepts || !current.term.ends_with(&rule.from) {
+                    continue;
+                }
+
+                let stem = &current.term[..current.term.len() - rule.from.len()];
+                if stem.is_empty() && rule.to.is_empty() {
+                    continue;
+                }
+                let term = format!("{}{}", stem, rule.to);
+                if !seen.insert((term.clone(), rule.output)) {
+                    continue;
+                }
+
+                let mut reasons = rule.reasons.to_vec();
+                reasons.extend(&current.reasons);
+                results.push(Deinflection {
+                    term,
+                    reasons,
+                    class: Some(rule.output),
+                });
+            }
+        }
+
+        results.retain(|d| d.class.is_none_or(WordClass::is_dictionary_form));
+        results
+    }
+}
+
+impl Deinflection {
+    /// Whether `entry` can be the dictionary form: any entry matches the
+    /// word itself, otherwise some sense must have a part of speech of the
+    /// deinflected class.
+    pub fn matches(&self, entry: &Entry) -> bool {
+        search::class_matches(self, entry)
+    }
+
+    /// Headwords to look the candidate up by. Forms of する are also looked
+    /// up without it, since nouns like 勉強 are listed without the verb.
+    pub fn lookup_terms(&self) -> Vec<&str> {
+        let mut terms = vec![self.term.as_str()];
+        if self.class == Some(WordClass::Suru) {
+            if let Some(stem) = self.term.strip_suffix("する").filter(|s| !s.is_empty()) {
+                terms.push(stem);
+            }
+        }
+        terms
+    }
+}
+
+/// Deinflects a single word with a freshly built [`Deinflector`].
+pub fn deinflect(word: &str) -> Vec<Deinflection> {
+    Deinflector::new().deinflect(word)
+}
+
+impl WordClass {
+    pub fn is_dictionary_form(self) -> bool {
+        !matches!(self, WordClass::Masu | WordClass::Te)
+    }
+
+    /// Whether a word with the given part of speech conjugates as this
+    /// class.
+    pub fn matches(self, pos: &PartOfSpeech) -> bool {
+        use PartOfSpeech::*;
+
+        match self {
+            WordClass::Ichidan => matches!(pos, Ichidan | IchidanKureru),
+            WordClass::Godan => matches!(
+                pos,
+                GodanAru
+                    | GodanBu
+                    | GodanGu
+                    | GodanKu
+                    | GodanIku
+                    | GodanMu
+                    | GodanNu
+                    | GodanRu
+                    | GodanRuIrregular
+                    | GodanSu
+                    | GodanTsu
+                    | GodanU
+                    | GodanUSpecial
+            ),
+            WordClass::Suru => matches!(pos, Suru | SuruIncluded | SuruSpecial),
+            WordClass::Kuru => matches!(pos, Kuru),
+            WordClass::IAdjective => matches!(pos, Adjective | AdjectiveYoiIi),
+            WordClass::Masu | WordClass::Te => false,
+        }
+    }
+}
+
+// Godan endings: dictionary, a, i, e and o stems, te and past.
+#[rustfmt::skip]
+pub(crate) const GODAN: &[[&str; 7]] = &[
+    ["う", "わ", "い", "え", "お", "って", "った"],
+    ["く", "か", "き", "け", "こ", "いて", "いた"],
+    ["ぐ", "が", "ぎ", "げ", "ご", "いで", "いだ"],
+    ["す", "さ", "し", "せ", "そ", "して", "した"],
+    ["つ", "た", "ち", "て", "と", "って", "った"],
+    ["ぬ", "な", "に", "ね", "の", "んで", "んだ"],
+    ["ぶ", "ば", "び", "べ", "ぼ", "んで", "んだ"],
+    ["む", "ま", "み", "め", "も", "んで", "んだ"],
+    ["る", "ら", "り", "れ", "ろ", "って", "った"],
+];
+
+// Endings added to ichidan stems: (ending, classes it may have, reasons).
+#[rustfmt::skip]
+const ICHIDAN: &[(&str, &[WordClass], &[Reason])] = &[
+    ("ない", &[WordClass::IAdjective], &[Reason::Negative]),
+    ("た", &[], &[Reason::Past]),
+    ("て", &[WordClass::Te], &[Reason::Te]),
+    ("ます", &[WordClass::Masu], &[Reason::Polite]),
+    ("られる", &[WordClass::Ichidan], &[Reason::PotentialOrPassive]),
+    ("れる", &[WordClass::Ichidan], &[Reason::Potential]),
+    ("させる", &[WordClass::Ichidan], &[Reason::Causative]),
+    ("よう", &[], &[Reason::Volitional]),
+    ("ろ", &[], &[Reason::Imperative]),
+    ("よ", &[], &[Reason::Imperative]),
+    ("れば", &[], &[Reason::Conditional]),
+    ("たら", &[], &[Reason::Tara]),
+    ("たり", &[], &[Reason::Tari]),
+    ("たい", &[WordClass::IAdjective], &[Reason::Desire]),
+    ("ず", &[], &[Reason::Zu]),
+];
+
+// Irregular forms of する and 来る: (inflected, dictionary, classes, reasons).
+#[rustfmt::skip]
+const IRREGULAR: &[(&str, &str, &[WordClass], &[Reason])] = &[
+    ("しない", "する", &[WordClass::IAdjective], &[Reason::Negative]),
+    ("した", "する", &[], &[Reason::Past]),
+    ("して", "する", &[WordClass::Te], &[Reason::Te]),
+    ("します", "する", &[WordClass::Masu], &[Reason::Polite]),
+    ("できる", "する", &[WordClass::Ichidan], &[Reason::Potential]),
+    ("される", "する", &[WordClass::Ichidan], &[Reason::Passive]),
+    ("させる", "する", &[WordClass::Ichidan], &[Reason::Causative]),
+    ("しよう", "する", &[], &[Reason::Volitional]),
+    ("しろ", "する", &[], &[Reason::Imperative]),
+    ("せよ", "する", &[], &[Reason::Imperative]),
+    ("すれば", "する", &[], &[Reason::Conditional]),
+    ("したら", "する", &[], &[Reason::Tara]),
+    ("したり", "する", &[], &[Reason::Tari]),
+    ("したい", "する", &[WordClass::IAdjective], &[Reason::Desire]),
+    ("せず", "する", &[], &[Reason::Zu]),
+    ("こない", "くる", &[WordClass::IAdjective], &[Reason::Negative]),
+    ("きた", "くる", &[], &[Reason::Past]),
+    ("きて", "くる", &[WordClass::Te], &[Reason::Te]),
+    ("きます", "くる", &[WordClass::Masu], &[Reason::Polite]),
+    ("こられる", "くる", &[WordClass::Ichidan], &[Reason::PotentialOrPassive]),
+    ("これる", "くる", &[WordClass::Ichidan], &[Reason::Potential]),
+    ("こさせる", "くる", &[WordClass::Ichidan], &[Reason::Causative]),
+    ("こよう", "くる", &[], &[Reason::Volitional]),
+    ("こい", "くる", &[], &[Reason::Imperative]),
+    ("くれば", "くる", &[], &[Reason::Conditional]),
+    ("きたら", "くる", &[], &[Reason::Tara]),
+    ("きたり", "くる", &[], &[Reason::Tari]),
+    ("きたい", "くる", &[WordClass::IAdjective], &[Reason::Desire]),
+    ("こず", "くる", &[], &[Reason::Zu]),
+];
+
+// (inflected, dictionary, classes, class of the result, reasons)
+type Ending = (
+    &'static str,
+    &'static str,
+    &'static [WordClass],
+    WordClass,
+    &'static [Reason],
+);
+
+// Endings of intermediate and adjective forms.
+#[rustfmt::skip]
+const OTHER: &[Ending] = &[
+    ("くない", "い", &[WordClass::IAdjective], WordClass::IAdjective, &[Reason::Negative]),
+    ("かった", "い", &[], WordClass::IAdjective, &[Reason::Past]),
+    ("くて", "い", &[WordClass::Te], WordClass::IAdjective, &[Reason::Te]),
+    ("く", "い", &[], WordClass::IAdjective, &[Reason::Adverbial]),
+    ("さ", "い", &[], WordClass::IAdjective, &[Reason::Noun]),
+    ("ければ", "い", &[], WordClass::IAdjective, &[Reason::Conditional]),
+    ("かったら", "い", &[], WordClass::IAdjective, &[Reason::Tara]),
+    ("かったり", "い", &[], WordClass::IAdjective, &[Reason::Tari]),
+    ("ました", "ます", &[], WordClass::Masu, &[Reason::Past]),
+    ("ません", "ます", &[], WordClass::Masu, &[Reason::Negative]),
+    ("ませんでした", "ます", &[], WordClass::Masu, &[Reason::Negative, Reason::Past]),
+    ("ましょう", "ます", &[], WordClass::Masu, &[Reason::Volitional]),
+    ("まして", "ます", &[WordClass::Te], WordClass::Masu, &[Reason::Te]),
+    ("ている", "て", &[WordClass::Ichidan], WordClass::Te, &[Reason::Progressive]),
+    ("てる", "て", &[WordClass::Ichidan], WordClass::Te, &[Reason::Progressive]),
+    ("でいる", "で", &[WordClass::Ichidan], WordClass::Te, &[Reason::Progressive]),
+    ("でる", "で", &[WordClass::Ichidan], WordClass::Te, &[Reason::Progressive]),
+    ("てしまう", "て", &[WordClass::Godan], WordClass::Te, &[Reason::Completion]),
+    ("でしまう", "で", &[WordClass::Godan], WordClass::Te, &[Reason::Completion]),
+    // 行く and its compounds geminate instead of taking い.
+    ("いって", "いく", &[WordClass::Te], WordClass::Godan, &[Reason::Te]),
+    ("いった", "いく", &[], WordClass::Godan, &[Reason::Past]),
+    ("行って", "行く", &[WordClass::Te], WordClass::Godan, &[Reason::Te]),
+    ("行った", "行く", &[], WordClass::Godan, &[Reason::Past]),
+];
+
+fn rules() -> Vec<Rule> {
+    let mut rules = Vec::new();
+    let mut push = |from: String, to: &str, input, output, reasons| {
+        rules.push(Rule {
+            from,
+            to: to.to_owned(),
+            input,
+            output,
+            reasons,
+        })
+    };
+
+    for &(ending, input, reasons) in ICHIDAN {
+        push(ending.to_owned(), "る", input, WordClass::Ichidan, reasons);
+    }
+
+    for row in GODAN {
+        let [dict, a, i, e, o, te, ta] = *row;
+        #[rustfmt::skip]
+        let forms: [(String, &'static [WordClass], &'static [Reason]); 14] = [
+            (format!("{}ない", a), &[WordClass::IAdjective], &[Reason::Negative]),
+            (ta.to_owned(), &[], &[Reason::Past]),
+            (te.to_owned(), &[WordClass::Te], &[Reason::Te]),
+            (format!("{}ます", i), &[WordClass::Masu], &[Reason::Polite]),
+            (format!("{}る", e), &[WordClass::Ichidan], &[Reason::Potential]),
+            (format!("{}れる", a), &[WordClass::Ichidan], &[Reason::Passive]),
+            (format!("{}せる", a), &[WordClass::Ichidan], &[Reason::Causative]),
+            (format!("{}う", o), &[], &[Reason::Volitional]),
+            (e.to_owned(), &[], &[Reason::Imperative]),
+            (format!("{}ば", e), &[], &[Reason::Conditional]),
+            (format!("{}ら", ta), &[], &[Reason::Tara]),
+            (format!("{}り", ta), &[], &[Reason::Tari]),
+            (format!("{}たい", i), &[WordClass::IAdjective], &[Reason::Desire]),
+            (format!("{}ず", a), &[], &[Reason::Zu]),
+        ];
+        for (from, input, reasons) in forms.iter().cloned() {
+            push(from, dict, input, WordClass::Godan, reasons);
+        }
+    }
+
+    for &(from, to, input, reasons) in IRREGULAR {
+        let output = if to == "する" {
+            WordClass::Suru
+        } else {
+            WordClass::Kuru
+        };
+        push(from.to_owned(), to, input, output, reasons);
+        // 来る is usually written in kanji, leaving only the ending in kana.
+        if output == WordClass::Kuru {
+            push(format!("来{}", &from[3..]), "来る", input, output, reasons);
+        }
+    }
+
+    for &(from, to, input, output, reasons) in OTHER {
+        push(from.to_owned(), to, input, output, reasons);
+    }
+
+    rules
+}
@@ -0,0 +1,414 @@
+//! Typed values for the entity codes declared in the JMdict and JMnedict
+//! DTDs.
+//!
+//! Every enum round-trips through both the entity code used in the XML
+//! (`v5k`, `uk`) and its English description. Codes missing from the tables
+//! below are kept in an `Unknown` variant so that newer releases of the
+//! dictionary still parse.
+
+use std::convert::Infallible;
+use std::str::FromStr;
+
+macro_rules! entity_enum {
+    (
+        $(#[$meta:meta])*
+        pub enum $name:ident {
+            $( $variant:ident => ($code:literal, $desc:literal), )*
+        }
+    ) => {
+        $(#[$meta])*
+        #[derive(Debug, Clone, PartialEq, Eq, Hash)]
+        pub enum $name {
+            $( $variant, )*
+            Unknown(String),
+        }
+
+        impl $name {
+            pub fn from_code(code: &str) -> Self {
+                match code {
+                    $( $code => $name::$variant, )*
+                    _ => $name::Unknown(code.to_owned()),
+                }
+            }
+
+            pub fn from_description(description: &str) -> Self {
+                match description {
+                    $( $desc => $name::$variant, )*
+                    _ => $name::Unknown(description.to_owned()),
+                }
+            }
+
+            pub fn code(&self) -> &str {
+                match self {
+                    $( $name::$variant => $code, )*
+                    $name::Unknown(s) => s,
+                }
+            }
+
+            pub fn description(&self) -> Option<&'static str> {
+                match self {
+                    $( $name::$variant => Some($desc), )*
+                    $name::Unknown(_) => None,
+                }
+            }
+        }
+
+        /// Accepts either an entity code or its description.
+        impl FromStr for $name {
+            type Err = Infallible;
+
+            fn from_str(s: &str) -> Result<Self, Self::Err> {
+                match $name::from_code(s) {
+                    $name::Unknown(_) => Ok($name::from_description(s)),
+                    known => Ok(known),
+                }
+            }
+        }
+
+        /// Serialized as the entity code.
+        #[cfg(feature = "serde")]
+        impl serde::Serialize for $name {
+            fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                serializer.serialize_str(self.code())
+            }
+        }
+
+        #[cfg(feature = "serde")]
+        impl<'de> serde::Deserialize<'de> for $name {
+            fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                let code = String::deserialize(deserializer)?;
+                Ok($name::from_code(&code))
+            }
+        }
+    };
+}
+
+entity_enum! {
+    pub enum PartOfSpeech {
+        PrenominalNounOrVerb => ("adj-f", "noun or verb acting prenominally"),
+        Adjective => ("adj-i", "adjective (keiyoushi)"),
+        AdjectiveYoiIi => ("adj-ix", "adjective (keiyoushi) - yoi/ii class"),
+        AdjectiveKari => ("adj-kari", "`kari' adjective (archaic)"),
+        AdjectiveKu => ("adj-ku", "`ku' adjective (archaic)"),
+        AdjectivalNoun => ("adj-na", "adjectival nouns or quasi-adjectives (keiyodoshi)"),
+        AdjectivalNounNari => ("adj-nari", "archaic/formal form of na-adjective"),
+        NounNo => ("adj-no", "nouns which may take the genitive case particle `no'"),
+        PrenounAdjectival => ("adj-pn", "pre-noun adjectival (rentaishi)"),
+        AdjectiveShiku => ("adj-shiku", "`shiku' adjective (archaic)"),
+        AdjectiveTaru => ("adj-t", "`taru' adjective"),
+        Adverb => ("adv", "adverb (fukushi)"),
+        AdverbTo => ("adv-to", "adverb taking the `to' particle"),
+        Auxiliary => ("aux", "auxiliary"),
+        AuxiliaryAdjective => ("aux-adj", "auxiliary adjective"),
+        AuxiliaryVerb => ("aux-v", "auxiliary verb"),
+        Conjunction => ("conj", "conjunction"),
+        Copula => ("cop", "copula"),
+        Counter => ("ctr", "counter"),
+        Expression => ("exp", "expressions (phrases, clauses, etc.)"),
+        Interjection => ("int", "interjection (kandoushi)"),
+        Noun => ("n", "noun (common) (futsuumeishi)"),
+        AdverbialNoun => ("n-adv", "adverbial noun (fukushitekimeishi)"),
+        ProperNoun => ("n-pr", "proper noun"),
+        NounPrefix => ("n-pref", "noun, used as a prefix"),
+        NounSuffix => ("n-suf", "noun, used as a suffix"),
+        TemporalNoun => ("n-t", "noun (temporal) (jisoumeishi)"),
+        Numeric => ("num", "numeric"),
+        Pronoun => ("pn", "pronoun"),
+        Prefix => ("pref", "prefix"),
+        Particle => ("prt", "particle"),
+        Suffix => ("suf", "suffix"),
+        Unclassified => ("unc", "unclassified"),
+        VerbUnspecified => ("v-unspec", "verb unspecified"),
+        Ichidan => ("v1", "Ichidan verb"),
+        IchidanKureru => ("v1-s", "Ichidan verb - kureru special class"),
+        NidanU => ("v2a-s", "Nidan verb with `u' ending (archaic)"),
+        NidanUpperBu => ("v2b-k", "Nidan verb (upper class) with `bu' ending (archaic)"),
+        NidanLowerBu => ("v2b-s", "Nidan verb (lower class) with `bu' ending (archaic)"),
+        NidanUpperDzu => ("v2d-k", "Nidan verb (upper class) with `dzu' ending (archaic)"),
+        NidanLowerDzu => ("v2d-s", "Nidan verb (lower class) with `dzu' ending (archaic)"),
+        NidanUpperGu => ("v2g-k", "Nidan verb (upper class) with `gu' ending (archaic)"),
+        NidanLowerGu => ("v2g-s", "Nidan verb (lower class) with `gu' ending (archaic)"),
+        NidanUpperFu => ("v2h-k", "Nidan verb (upper class) with `hu/fu' ending (archaic)"),
+        NidanLowerFu => ("v2h-s", "Nidan verb (lower class) with `hu/fu' ending (archaic)"),
+        NidanUpperKu => ("v2k-k", "Nidan verb (upper class) with `ku' ending (archaic)"),
+        NidanLowerKu => ("v2k-s", "Nidan verb (lower class) with `ku' ending (archaic)"),
+        NidanUpperMu => ("v2m-k", "Nidan verb (upper class) with `mu' ending (archaic)"),
+        NidanLowerMu => ("v2m-s", "Nidan verb (lower class) with `mu' ending (archaic)"),
+        NidanLowerNu => ("v2n-s", "Nidan verb (lower class) with `nu' ending (archaic)"),
+        NidanUpperRu => ("v2r-k", "Nidan verb (upper class) with `ru' ending (archaic)"),
+        NidanLowerRu => ("v2r-s", "Nidan verb (lower class) with `ru' ending (archaic)"),
+        NidanLowerSu => ("v2s-s", "Nidan verb (lower class) with `su' ending (archaic)"),
+        NidanUpperTsu => ("v2t-k", "Nidan verb (upper class) with `tsu' ending (archaic)"),
+        NidanLowerTsu => ("v2t-s", "Nidan verb (lower class) with `tsu' ending (archaic)"),
+        NidanLowerUWe => ("v2w-s", "Nidan verb (lower class) with `u' ending and `we' conjugation (archaic)"),
+        NidanUpperYu => ("v2y-k", "Nidan verb (upper class) with `yu' ending (archaic)"),
+        NidanLowerYu => ("v2y-s", "Nidan verb (lower class) with `yu' ending (archaic)"),
+        NidanLowerZu => ("v2z-s", "Nidan verb (lower class) with `zu' ending (archaic)"),
+        YodanBu => ("v4b", "Yodan verb with `bu' ending (archaic)"),
+        YodanGu => ("v4g", "Yodan verb with `gu' ending (archaic)"),
+        YodanFu => ("v4h", "Yodan verb with `hu/fu' ending (archaic)"),
+        YodanKu => ("v4k", "Yodan verb with `ku' ending (archaic)"),
+        YodanMu => ("v4m", "Yodan verb with `mu' ending (archaic)"),
+        YodanNu => ("v4n", "Yodan verb with `nu' ending (archaic)"),
+        YodanRu => ("v4r", "Yodan verb with `ru' ending (archaic)"),
+        YodanSu => ("v4s", "Yodan verb with `su' ending (archaic)"),
+        YodanTsu => ("v4t", "Yodan verb with `tsu' ending (archaic)"),
+        GodanAru => ("v5aru", "Godan verb - -aru special class"),
+        GodanBu => ("v5b", "Godan verb with `bu' ending"),
+        GodanGu => ("v5g", "Godan verb with `gu' ending"),
+        GodanKu => ("v5k", "Godan verb with `ku' ending"),
+        GodanIku => ("v5k-s", "Godan verb - Iku/Yuku special class"),
+        GodanMu => ("v5m", "Godan verb with `mu' ending"),
+        GodanNu => ("v5n", "Godan verb with `nu' ending"),
+        GodanRu => ("v5r", "Godan verb with `ru' ending"),
+        GodanRuIrregular => ("v5r-i", "Godan verb with `ru' ending (irregular verb)"),
+        GodanSu => ("v5s", "Godan verb with `su' ending"),
+        GodanTsu => ("v5t", "Godan verb with `tsu' ending"),
+        GodanU => ("v5u", "Godan verb with `u' ending"),
+        GodanUSpecial => ("v5u-s", "Godan verb with `u' ending (special class)"),
+        GodanUru => ("v5uru", "Godan verb - Uru old class verb (old form of Eru)"),
+        Intransitive => ("vi", "intransitive verb"),
+        Kuru => ("vk", "Kuru verb - special class"),
+        IrregularNu => ("vn", "irregular nu verb"),
+        IrregularRu => ("vr", "irregular ru verb, plain form ends with -ri"),
+        Suru => ("vs", "noun or participle which takes the aux. verb suru"),
+        SuruPrecursor => ("vs-c", "su verb - precursor to the modern suru"),
+        SuruIncluded => ("vs-i", "suru verb - included"),
+        SuruSpecial => ("vs-s", "suru verb - special class"),
+        Transitive => ("vt", "transitive verb"),
+        IchidanZuru => ("vz", "Ichidan verb - zuru verb (alternative form of -jiru verbs)"),
+    }
+}
+
+entity_enum! {
+    pub enum Misc {
+        Abbreviation => ("abbr", "abbreviation"),
+        Archaic => ("arch", "archaic"),
+        Character => ("char", "character"),
+        Children => ("chn", "children's language"),
+        Colloquial => ("col", "colloquial"),
+        Company => ("company", "company name"),
+        Creature => ("creat", "creature"),
+        Dated => ("dated", "dated term"),
+        Deity => ("dei", "deity"),
+        Derogatory => ("derog", "derogatory"),
+        Document => ("doc", "document"),
+        Euphemistic => ("euph", "euphemistic"),
+        Event => ("ev", "event"),
+        Familiar => ("fam", "familiar language"),
+        Female => ("fem", "female term or language"),
+        Fiction => ("fict", "fiction"),
+        Formal => ("form", "formal or literary term"),
+        Given => ("given", "given name or forename, gender not specified"),
+        Group => ("group", "group"),
+        Historical => ("hist", "historical term"),
+        Honorific => ("hon", "honorific or respectful (sonkeigo) language"),
+        Humble => ("hum", "humble (kenjougo) language"),
+        Idiomatic => ("id", "idiomatic expression"),
+        Jocular => ("joc", "jocular, humorous term"),
+        Legend => ("leg", "legend"),
+        MangaSlang => ("m-sl", "manga slang"),
+        Male => ("male", "male term or language"),
+        Mythology => ("myth", "mythology"),
+        InternetSlang => ("net-sl", "Internet slang"),
+        Object => ("obj", "object"),
+        Obscure => ("obsc", "obscure term"),
+        Obsolete => ("obs", "obsolete term"),
+        Onomatopoeic => ("on-mim", "onomatopoeic or mimetic word"),
+        Organization => ("organization", "organization name"),
+        Other => ("oth", "other"),
+        Person => ("person", "full name of a particular person"),
+        Place => ("place", "place name"),
+        Poetical => ("poet", "poetical term"),
+        Polite => ("pol", "polite (teineigo) language"),
+        Product => ("product", "product name"),
+        Proverb => ("proverb", "proverb"),
+        Quotation => ("quote", "quotation"),
+        Rare => ("rare", "rare term"),
+        Religion => ("relig", "religion"),
+        Sensitive => ("sens", "sensitive"),
+        Service => ("serv", "service"),
+        Ship => ("ship", "ship name"),
+        Slang => ("sl", "slang"),
+        Station => ("station", "railway station"),
+        Surname => ("surname", "family or surname"),
+        UsuallyKana => ("uk", "word usually written using kana alone"),
+        Unclassified => ("unclass", "unclassified name"),
+        Vulgar => ("vulg", "vulgar expression or word"),
+        Work => ("work", "work of art, literature, music, etc. name"),
+        Rude => ("X", "rude or X-rated term (not displayed in educational software)"),
+        Yojijukugo => ("yoji", "yojijukugo"),
+    }
+}
+
+entity_enum! {
+    pub enum Field {
+        Agriculture => ("agric", "agriculture"),
+        Anatomy => ("anat", "anatomy"),
+        Archeology => ("archeol", "archeology"),
+        Architecture => ("archit", "architecture"),
+        Art => ("art", "art, aesthetics"),
+        Astronomy => ("astron", "astronomy"),
+        Audiovisual => ("audvid", "audiovisual"),
+        Aviation => ("aviat", "aviation"),
+        Baseball => ("baseb", "baseball"),
+        Biochemistry => ("biochem", "biochemistry"),
+        Biology => ("biol", "biology"),
+        Botany => ("bot", "botany"),
+        Boxing => ("boxing", "boxing"),
+        Buddhism => ("Buddh", "Buddhism"),
+        Business => ("bus", "business"),
+        CardGames => ("cards", "card games"),
+        Chemistry => ("chem", "chemistry"),
+        ChineseMythology => ("chmyth", "Chinese mythology"),
+        Christianity => ("Christn", "Christianity"),
+        CivilEngineering => ("civeng", "civil engineering"),
+        Clothing => ("cloth", "clothing"),
+        Computing => ("comp", "computing"),
+        Crystallography => ("cryst", "crystallography"),
+        Dentistry => ("dent", "dentistry"),
+        Ecology => ("ecol", "ecology"),
+        Economics => ("econ", "economics"),
+        Electricity => ("elec", "electricity, elec. eng."),
+        Electronics => ("electr", "electronics"),
+        Embryology => ("embryo", "embryology"),
+        Engineering => ("engr", "engineering"),
+        Entomology => ("ent", "entomology"),
+        FigureSkating => ("figskt", "figure skating"),
+        Film => ("film", "film"),
+        Finance => ("finc", "finance"),
+        Fishing => ("fish", "fishing"),
+        Food => ("food", "food, cooking"),
+        Gardening => ("gardn", "gardening, horticulture"),
+        Genetics => ("genet", "genetics"),
+        Geography => ("geogr", "geography"),
+        Geology => ("geol", "geology"),
+        Geometry => ("geom", "geometry"),
+        Go => ("go", "go (game)"),
+        Golf => ("golf", "golf"),
+        Grammar => ("gramm", "grammar"),
+        GreekMythology => ("grmyth", "Greek mythology"),
+        Hanafuda => ("hanaf", "hanafuda"),
+        HorseRacing => ("horse", "horse racing"),
+        Internet => ("internet", "Internet"),
+        JapaneseMythology => ("jpmyth", "Japanese mythology"),
+        Kabuki => ("kabuki", "kabuki"),
+        Law => ("law", "law"),
+        Linguistics => ("ling", "linguistics"),
+        Logic => ("logic", "logic"),
+        MartialArts => ("MA", "martial arts"),
+        Mahjong => ("mahj", "mahjong"),
+        Manga => ("manga", "manga"),
+        Mathematics => ("math", "mathematics"),
+        MechanicalEngineering => ("mech", "mechanical engineering"),
+        Medicine => ("med", "medicine"),
+        Meteorology => ("met", "meteorology"),
+        Military => ("mil", "military"),
+        Mineralogy => ("min", "mineralogy"),
+        Mining => ("mining", "mining"),
+        Motorsport => ("motor", "motorsport"),
+        Music => ("music", "music"),
+        Noh => ("noh", "noh"),
+        Ornithology => ("ornith", "ornithology"),
+        Paleontology => ("paleo", "paleontology"),
+        Pathology => ("pathol", "pathology"),
+        Pharmacology => ("pharm", "pharmacology"),
+        Philosophy => ("phil", "philosophy"),
+        Photography => ("photo", "photography"),
+        Physics => ("physics", "physics"),
+        Physiology => ("physiol", "physiology"),
+        Politics => ("politics", "politics"),
+        Printing => ("print", "printing"),
+        ProfessionalWrestling => ("prowres", "professional wrestling"),
+        Psychiatry => ("psy", "psychiatry"),
+        Psychoanalysis => ("psyanal", "psychoanalysis"),
+        Psychology => ("psych", "psychology"),
+        Railway => ("rail", "railway"),
+        RomanMythology => ("rommyth", "Roman mythology"),
+        Shinto => ("Shinto", "Shinto"),
+        Shogi => ("shogi", "shogi"),
+        Skiing => ("ski", "skiing"),
+        Sports => ("sports", "sports"),
+        Statistics => ("stat", "statistics"),
+        StockMarket => ("stockm", "stock market"),
+        Sumo => ("sumo", "sumo"),
+        Surgery => ("surg", "surgery"),
+        Telecommunications => ("telec", "telecommunications"),
+        Trademark => ("tradem", "trademark"),
+        Television => ("tv", "television"),
+        Veterinary => ("vet", "veterinary terms"),
+        VideoGames => ("vidg", "video games"),
+        Zoology => ("zool", "zoology"),
+    }
+}
+
+entity_enum! {
+    pub enum Dialect {
+        Brazilian => ("bra", "Brazilian"),
+        Hokkaido => ("hob", "Hokkaido-ben"),
+        Kansai => ("ksb", "Kansai-ben"),
+        Kantou => ("ktb", "Kantou-ben"),
+        Kyoto => ("kyb", "Kyoto-ben"),
+        Kyuushuu => ("kyu", "Kyuushuu-ben"),
+        Nagano => ("nab", "Nagano-ben"),
+        Osaka => ("osb", "Osaka-ben"),
+        Ryuukyuu => ("rkb", "Ryuukyuu-ben"),
+        Touhoku => ("thb", "Touhoku-ben"),
+        Tosa => ("tsb", "Tosa-ben"),
+        Tsugaru => ("tsug", "Tsugaru-ben"),
+    }
+}
+
+entity_enum! {
+    pub enum KanjiInfo {
+        Ateji => ("ateji", "ateji (phonetic) reading"),
+        IrregularKana => ("ik", "word containing irregular kana usage"),
+        IrregularKanji => ("iK", "word containing irregular kanji usage"),
+        IrregularOkurigana => ("io", "irregular okurigana usage"),
+        OutdatedKanji => ("oK", "word containing out-dated kanji or kanji usage"),
+        RareKanji => ("rK", "rarely used kanji form"),
+        SearchOnlyKanji => ("sK", "search-only kanji form"),
+    }
+}
+
+entity_enum! {
+    pub enum ReadingInfo {
+        Gikun => ("gikun", "gikun (meaning as reading) or jukujikun (special kanji reading)"),
+        IrregularKana => ("ik", "word containing irregular kana usage"),
+        OutdatedKana => ("ok", "out-dated or obsolete kana usage"),
+        UsuallyKanji => ("uK", "word usually written using kanji alone"),
+        RareKana => ("rk", "rarely used kana form"),
+        SearchOnlyKana => ("sk", "search-only kana form"),
+    }
+}
+
+entity_enum! {
+    pub enum NameType {
+        Character => ("char", "character"),
+        Company => ("company", "company name"),
+        Creature => ("creat", "creature"),
+        Deity => ("dei", "deity"),
+        Document => ("doc", "document"),
+        Event => ("ev", "event"),
+        Female => ("fem", "female given name or forename"),
+        Fiction => ("fict", "fiction"),
+        Given => ("given", "given name or forename, gender not specified"),
+        Group => ("group", "group"),
+        Legend => ("leg", "legend"),
+        Male => ("masc", "male given name or forename"),
+        Mythology => ("myth", "mythology"),
+        Object => ("obj", "object"),
+        Organization => ("organization", "organization name"),
+        Other => ("oth", "other"),
+        Person => ("person", "full name of a particular person"),
+        Place => ("place", "place name"),
+        Product => ("product", "product name"),
+        Religion => ("relig", "religion"),
+        Service => ("serv", "service"),
+        Ship => ("ship", "ship name"),
+        Station => ("station", "railway station"),
+        Surname => ("surname", "family or surname"),
+        Unclassified => ("unclass", "unclassified name"),
+        Work => ("work", "work of art, literature, music, etc. name"),
+    }
+}
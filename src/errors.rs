@@ -3,6 +3,7 @@ use std::error;
 use std::fmt;
 use std::io;
 use std::num;
+use std::string;
 
 #[derive(Debug)]
 pub enum ParseError {
@@ -10,8 +11,10 @@ pub enum ParseError {
     Xml(XmlError),
     ParseString(String),
     ParseInt(num::ParseIntError),
+    Utf8(string::FromUtf8Error),
     ParseEnum(ParseEnumError),
     ParseLanguage6391(String),
+    ParseLanguage6392(String),
     ParseLanguage6393(String),
 }
 
@@ -22,10 +25,14 @@ impl fmt::Display for ParseError {
             ParseError::Xml(ref err) => write!(f, "XML error: {}", err),
             ParseError::ParseString(ref err_str) => write!(f, "Parse error: {}", err_str),
             ParseError::ParseInt(ref err) => write!(f, "Parse error: {}", err),
+            ParseError::Utf8(ref err) => write!(f, "UTF-8 error: {}", err),
             ParseError::ParseEnum(ref err) => write!(f, "Parse error: {}", err),
             ParseError::ParseLanguage6391(ref lang) => {
                 write!(f, "invalid ISO 639-1 language code: {}", lang)
             }
+            ParseError::ParseLanguage6392(ref lang) => {
+                write!(f, "invalid ISO 639-2 language code: {}", lang)
+            }
             ParseError::ParseLanguage6393(ref lang) => {
                 write!(f, "invalid ISO 639-3 language code: {}", lang)
             }
@@ -39,6 +46,7 @@ impl error::Error for ParseError {
             ParseError::IO(ref err) => Some(err),
             ParseError::Xml(ref err) => Some(err),
             ParseError::ParseInt(ref err) => Some(err),
+            ParseError::Utf8(ref err) => Some(err),
             ParseError::ParseEnum(ref err) => Some(err),
             _ => None,
         }
@@ -63,6 +71,12 @@ impl From<num::ParseIntError> for ParseError {
     }
 }
 
+impl From<string::FromUtf8Error> for ParseError {
+    fn from(err: string::FromUtf8Error) -> Self {
+        ParseError::Utf8(err)
+    }
+}
+
 impl From<ParseEnumError> for ParseError {
     fn from(err: ParseEnumError) -> Self {
         ParseError::ParseEnum(err)
@@ -75,6 +89,7 @@ pub enum XmlError {
     MissingTag(String),
     MissingAttr(String),
     MissingText,
+    UnexpectedEof(String),
 }
 
 impl fmt::Display for XmlError {
@@ -84,6 +99,9 @@ impl fmt::Display for XmlError {
             XmlError::MissingTag(ref name) => write!(f, "XML tag missing: {}", name),
             XmlError::MissingAttr(ref name) => write!(f, "XML element attribute missing: {}", name),
             XmlError::MissingText => write!(f, "XML element text missing"),
+            XmlError::UnexpectedEof(ref name) => {
+                write!(f, "XML ended unexpectedly inside: {}", name)
+            }
         }
     }
 }
@@ -103,6 +121,60 @@ impl From<roxmltree::Error> for XmlError {
     }
 }
 
+#[derive(Debug)]
+pub enum CacheError {
+    IO(io::Error),
+    // Raised when rebuilding a stale cache from the source file.
+    Parse(ParseError),
+    // The file is not a cache, or is a cache of another dictionary.
+    InvalidHeader,
+    // The cache was written in another version of the format.
+    FormatVersion(u32),
+    // The source file has changed since the cache was written.
+    SourceChanged,
+    // The cache was filtered to other languages than requested.
+    LanguagesChanged,
+    Corrupt,
+}
+
+impl fmt::Display for CacheError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            CacheError::IO(ref err) => write!(f, "IO error: {}", err),
+            CacheError::Parse(ref err) => write!(f, "{}", err),
+            CacheError::InvalidHeader => write!(f, "not a cache of this dictionary"),
+            CacheError::FormatVersion(version) => {
+                write!(f, "unsupported cache format version: {}", version)
+            }
+            CacheError::SourceChanged => write!(f, "source file changed since caching"),
+            CacheError::LanguagesChanged => write!(f, "cache holds other languages"),
+            CacheError::Corrupt => write!(f, "cache is truncated or corrupt"),
+        }
+    }
+}
+
+impl error::Error for CacheError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match *self {
+            CacheError::IO(ref err) => Some(err),
+            CacheError::Parse(ref err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for CacheError {
+    fn from(err: io::Error) -> Self {
+        CacheError::IO(err)
+    }
+}
+
+impl From<ParseError> for CacheError {
+    fn from(err: ParseError) -> Self {
+        CacheError::Parse(err)
+    }
+}
+
 #[derive(Debug)]
 pub struct ParseEnumError {
     value: String,
@@ -0,0 +1,239 @@
+//! Alignment of a written form with its reading, for ruby text.
+//!
+//! Kana in the written form are matched against the reading first, which
+//! splits it into runs of kanji and the part of the reading each run takes.
+//! Each run is then split further into single kanji using their on and kun
+//! readings from Kanjidic, allowing for rendaku (ひ → び) and gemination
+//! (がく → がっ). Runs that cannot be split, such as the jukujikun 今日,
+//! keep the reading as a whole.
+
+use crate::index::IndexedKanjidic;
+use crate::kana;
+use crate::kanjidic::ReadingType;
+
+/// Part of a written form, with the reading shown above it if it is not
+/// kana.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Segment {
+    pub text: String,
+    pub ruby: Option<String>,
+}
+
+// Bound on the number of ways to split a reading between runs of kanji.
+const MAX_SPLITS: usize = 64;
+
+impl IndexedKanjidic {
+    /// Splits `written` into segments with their part of `reading`, e.g.
+    /// 取/と, り, 引/ひ, き for "取り引き" read "とりひき".
+    pub fn furigana(&self, written: &str, reading: &str) -> Vec<Segment> {
+        let runs = runs(written);
+        let original: Vec<char> = reading.chars().collect();
+        let hiragana: Vec<char> = kana::to_hiragana(reading).chars().collect();
+
+        let mut splits = Vec::new();
+        split_runs(&runs, &hiragana, 0, &mut Vec::new(), &mut splits);
+
+        // Prefer the split that lets the most kanji runs be divided into
+        // single characters.
+        let mut best: Option<(usize, Vec<Segment>)> = None;
+        for split in splits {
+            let mut aligned = 0;
+            let mut segments = Vec::new();
+            for (run, &(start, end)) in runs.iter().zip(&split) {
+                let text: String = run.chars.iter().collect();
+                if !run.kanji {
+                    segments.push(Segment { text, ruby: None });
+                    continue;
+                }
+
+                match self.align_kanji(&run.chars, &hiragana[start..end], None) {
+                    Some(lens) => {
+                        aligned += 1;
+                        let mut at = start;
+                        for (c, len) in run.chars.iter().zip(lens) {
+                            segments.push(Segment {
+                                text: c.to_string(),
+                                ruby: Some(original[at..at + len].iter().collect()),
+                            });
+                            at += len;
+                        }
+                    }
+                    None => segments.push(Segment {
+                        text,
+                        ruby: Some(original[start..end].iter().collect()),
+                    }),
+                }
+            }
+
+            if best.as_ref().is_none_or(|(a, _)| aligned > *a) {
+                best = Some((aligned, segments));
+            }
+        }
+
+        match best {
+            Some((_, segments)) => segments,
+            None => vec![Segment {
+                text: written.to_owned(),
+                ruby: Some(reading.to_owned()).filter(|r| r != written),
+            }],
+        }
+    }
+
+    /// Lengths of the reading taken by each kanji, if every kanji can be
+    /// given one of its readings.
+    fn align_kanji(
+        &self,
+        kanji: &[char],
+        reading: &[char],
+        prev: Option<char>,
+    ) -> Option<Vec<usize>> {
+        let (&first, rest) = match kanji.split_first() {
+            Some(split) => split,
+            None => return Some(Vec::new()).filter(|_| reading.is_empty()),
+        };
+
+        // 々 repeats the previous kanji, often with rendaku.
+        let literal = match (first, prev) {
+            ('々', Some(prev)) => prev,
+            _ => first,
+        };
+        let mut candidates = self.kanji_readings(literal);
+        candidates.sort_by_key(|r| std::cmp::Reverse(r.len()));
+
+        for candidate in candidates {
+            if reading.starts_with(&candidate) {
+                if let Some(mut lens) =
+                    self.align_kanji(rest, &reading[candidate.len()..], Some(literal))
+                {
+                    lens.insert(0, candidate.len());
+                    return Some(lens);
+                }
+            }
+        }
+        None
+    }
+
+    /// Possible readings of a kanji in hiragana, with their rendaku and
+    /// geminated variants.
+    fn kanji_readings(&self, literal: char) -> Vec<Vec<char>> {
+        let entry = match self.find_literal(&literal.to_string()) {
+            Some(entry) => entry,
+            None => return Vec::new(),
+        };
+
+        let readings = entry.reading_meanings.iter().flat_map(|rm| &rm.readings);
+        let base = readings.filter_map(|r| match r.typ {
+            ReadingType::Onyomi(..) => Some(kana::to_hiragana(&r.value)),
+            ReadingType::Kunyomi(_) => {
+                let value = r.value.trim_matches('-');
+                Some(value.split('.').next().unwrap_or(value).to_owned())
+            }
+            _ => None,
+        });
+        let base = base.chain(entry.nanori_readings.iter().cloned());
+
+        let mut variants: Vec<Vec<char>> = Vec::new();
+        for reading in base {
+            let chars: Vec<char> = reading.chars().collect();
+            if chars.is_empty() {
+                continue;
+            }
+
+            let mut forms = vec![chars.clone()];
+            for voiced in voiced(chars[0]) {
+                let mut form = chars.clone();
+                form[0] = voiced;
+                forms.push(form);
+            }
+            if chars.len() > 1 && "つくちき".contains(chars[chars.len() - 1]) {
+                let mut form = chars.clone();
+                *form.last_mut().unwrap() = 'っ';
+                forms.push(form);
+            }
+
+            for form in forms {
+                if !variants.contains(&form) {
+                    variants.push(form);
+                }
+            }
+        }
+        variants
+    }
+}
+
+struct Run {
+    chars: Vec<char>,
+    kanji: bool,
+}
+
+// Splits text into alternating runs of kana and of other characters.
+fn runs(text: &str) -> Vec<Run> {
+    let mut runs: Vec<Run> = Vec::new();
+    for c in text.chars() {
+        let kanji = !kana::is_kana(c);
+        match runs.last_mut() {
+            Some(run) if run.kanji == kanji => run.chars.push(c),
+            _ => runs.push(Run {
+                chars: vec![c],
+                kanji,
+            }),
+        }
+    }
+    runs
+}
+
+// Every way to give each run a range of the reading: kana runs must match
+// the reading exactly and kanji runs take at least one character.
+fn split_runs(
+    runs: &[Run],
+    reading: &[char],
+    at: usize,
+    current: &mut Vec<(usize, usize)>,
+    splits: &mut Vec<Vec<(usize, usize)>>,
+) {
+    if splits.len() >= MAX_SPLITS {
+        return;
+    }
+
+    let (run, rest) = match runs.split_first() {
+        Some(split) => split,
+        None => {
+            if at == reading.len() {
+                splits.push(current.clone());
+            }
+            return;
+        }
+    };
+
+    let ends: Vec<usize> = if run.kanji {
+        // Leave at least one character for each later kanji run.
+        let later = rest.iter().filter(|r| r.kanji).count();
+        (at + 1..=reading.len().saturating_sub(later)).collect()
+    } else {
+        let kana = kana::to_hiragana(&run.chars.iter().collect::<String>());
+        let kana: Vec<char> = kana.chars().collect();
+        if reading[at..].starts_with(&kana) {
+            vec![at + kana.len()]
+        } else {
+            Vec::new()
+        }
+    };
+
+    for end in ends {
+        current.push((at, end));
+        split_runs(rest, reading, end, current, splits);
+        current.pop();
+    }
+}
+
+// Voiced forms of a kana: が for か, and both ば and ぱ for は.
+fn voiced(c: char) -> Vec<char> {
+    let shift = |n| std::char::from_u32(c as u32 + n);
+    if "はひふへほ".contains(c) {
+        shift(1).into_iter().chain(shift(2)).collect()
+    } else if "かきくけこさしすせそたちつてと".contains(c) {
+        shift(1).into_iter().collect()
+    } else {
+        Vec::new()
+    }
+}
@@ -0,0 +1,124 @@
+//! Edit distance that treats near-identical kana as cheap substitutions.
+//!
+//! Insertions, deletions and substitutions cost 1. Substituting a kana for
+//! its small or large counterpart (や/ゃ, つ/っ) or for the same kana with
+//! or without dakuten or handakuten (か/が, は/ぱ) costs 0.5. Hiragana and
+//! katakana of the same sound, and upper and lower case letters, are equal.
+
+use crate::kana;
+
+/// Cost of substituting one kana for a near-identical one.
+pub const CHEAP_EDIT: f32 = 0.5;
+
+const VOICED: &str = "がぎぐげござじずぜぞだぢづでどばびぶべぼぱぴぷぺぽゔ";
+const UNVOICED: &str = "かきくけこさしすせそたちつてとはひふへほはひふへほう";
+const SMALL: &str = "ぁぃぅぇぉっゃゅょゎゕゖ";
+const LARGE: &str = "あいうえおつやゆよわかけ";
+
+/// Text with katakana turned into hiragana and letters lowercased, as
+/// compared by the distance. Indexes keep their keys folded so that each
+/// key is folded once rather than on every search.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Folded(Vec<char>);
+
+impl Folded {
+    pub(crate) fn new(text: &str) -> Self {
+        Folded(text.chars().map(fold).collect())
+    }
+}
+
+pub fn distance(a: &str, b: &str) -> f32 {
+    bounded_distance(&Folded::new(a).0, &Folded::new(b).0, f32::INFINITY).unwrap_or(f32::INFINITY)
+}
+
+/// Like [`distance`], but gives up and returns `None` as soon as the
+/// distance is known to exceed `max`.
+pub fn distance_within(a: &str, b: &str, max: f32) -> Option<f32> {
+    folded_distance_within(&Folded::new(a), &Folded::new(b), max)
+}
+
+pub(crate) fn folded_distance_within(a: &Folded, b: &Folded, max: f32) -> Option<f32> {
+    let (a, b) = (&a.0, &b.0);
+    if (a.len() as f32 - b.len() as f32).abs() > max {
+        return None;
+    }
+    bounded_distance(a, b, max)
+}
+
+fn bounded_distance(a: &[char], b: &[char], max: f32) -> Option<f32> {
+    let mut prev: Vec<f32> = (0..=b.len()).map(|j| j as f32).collect();
+    let mut cur = vec![0.0; b.len() + 1];
+    for i in 1..=a.len() {
+        cur[0] = i as f32;
+        for j in 1..=b.len() {
+            let substitute = prev[j - 1] + substitution_cost(a[i - 1], b[j - 1]);
+            cur[j] = substitute.min(prev[j] + 1.0).min(cur[j - 1] + 1.0);
+        }
+        // Costs never decrease from one row to the next, so once every
+        // cell of a row is over `max` the final distance is too.
+        if cur.iter().all(|&d| d > max) {
+            return None;
+        }
+        std::mem::swap(&mut prev, &mut cur);
+    }
+
+    Some(prev[b.len()]).filter(|&d| d <= max)
+}
+
+/// Candidates within `max` of `query`, excluding exact matches, sorted by
+/// distance and then text.
+pub fn nearest<'a>(
+    candidates: impl Iterator<Item = &'a str>,
+    query: &str,
+    max: f32,
+) -> Vec<(&'a str, f32)> {
+    let query = Folded::new(query);
+    rank(
+        candidates
+            .filter_map(|c| folded_distance_within(&query, &Folded::new(c), max).map(|d| (c, d))),
+    )
+}
+
+/// Like [`nearest`], for candidates paired with their folded text.
+pub(crate) fn nearest_folded<'a>(
+    candidates: impl Iterator<Item = (&'a str, &'a Folded)>,
+    query: &str,
+    max: f32,
+) -> Vec<(&'a str, f32)> {
+    let query = Folded::new(query);
+    rank(
+        candidates
+            .filter_map(|(c, folded)| folded_distance_within(&query, folded, max).map(|d| (c, d))),
+    )
+}
+
+fn rank<'a>(near: impl Iterator<Item = (&'a str, f32)>) -> Vec<(&'a str, f32)> {
+    let mut near: Vec<_> = near.filter(|&(_, d)| d > 0.0).collect();
+    near.sort_by(|(a, da), (b, db)| da.total_cmp(db).then(a.cmp(b)));
+    near
+}
+
+fn substitution_cost(a: char, b: char) -> f32 {
+    if a == b {
+        0.0
+    } else if base(a) == base(b) {
+        CHEAP_EDIT
+    } else {
+        1.0
+    }
+}
+
+fn fold(c: char) -> char {
+    let c = kana::to_hiragana_char(c);
+    c.to_lowercase().next().unwrap_or(c)
+}
+
+// The kana with dakuten removed and small kana enlarged.
+fn base(c: char) -> char {
+    for (from, to) in [(VOICED, UNVOICED), (SMALL, LARGE)].iter() {
+        if let Some(i) = from.chars().position(|v| v == c) {
+            return to.chars().nth(i).unwrap_or(c);
+        }
+    }
+    c
+}
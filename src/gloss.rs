@@ -0,0 +1,174 @@
+//! Inverted index over English glosses.
+
+use crate::fuzzy::{self, Folded};
+use crate::jmdict::Entry;
+use crate::language::Language;
+use std::collections::HashMap;
+
+/// Position of a gloss: entry, sense and gloss indices.
+type GlossRef = (usize, usize, usize);
+
+/// Maps each word of every English gloss to the glosses containing it.
+#[derive(Debug, Default)]
+pub struct GlossIndex {
+    postings: HashMap<String, Posting>,
+}
+
+#[derive(Debug, Default)]
+struct Posting {
+    // The word folded for fuzzy search.
+    folded: Folded,
+    refs: Vec<GlossRef>,
+}
+
+impl GlossIndex {
+    pub fn new(entries: &[Entry]) -> Self {
+        let mut postings: HashMap<_, Posting> = HashMap::new();
+
+        for (e, entry) in entries.iter().enumerate() {
+            for (s, sense) in entry.sense.iter().enumerate() {
+                for (g, gloss) in sense.gloss.iter().enumerate() {
+                    let content = match &gloss.content {
+                        Some(content) if gloss.lang == Language::ENGLISH => content,
+                        _ => continue,
+                    };
+                    for token in tokenize(content) {
+                        let refs = &mut postings.entry(token).or_default().refs;
+                        if refs.last() != Some(&(e, s, g)) {
+                            refs.push((e, s, g));
+                        }
+                    }
+                }
+            }
+        }
+
+        for (word, posting) in &mut postings {
+            posting.folded = Folded::new(word);
+        }
+        GlossIndex { postings }
+    }
+
+    /// Glosses containing every word of `query`, with their scores, best
+    /// first.
+    pub fn search(&self, entries: &[Entry], query: &str) -> Vec<(GlossRef, i32)> {
+        let tokens = tokenize(query);
+        let mut lists: Vec<_> = match tokens
+            .iter()
+            .map(|t| self.postings.get(t).map(|p| &p.refs))
+            .collect::<Option<Vec<_>>>()
+        {
+            Some(lists) if !lists.is_empty() => lists,
+            _ => return Vec::new(),
+        };
+
+        // Intersect starting from the rarest word.
+        lists.sort_by_key(|l| l.len());
+        let mut refs = lists[0].clone();
+        for list in &lists[1..] {
+            refs.retain(|r| list.binary_search(r).is_ok());
+        }
+
+        let query = normalize(query);
+        let mut hits: Vec<_> = refs
+            .into_iter()
+            .map(|r| (r, score(entries, r, &query, tokens.len())))
+            .collect();
+        hits.sort_by(|(a, sa), (b, sb)| sb.cmp(sa).then(a.cmp(b)));
+        hits
+    }
+
+    /// Glosses whose words are each within a total edit distance of `max`
+    /// of the words of `query`, with that distance, closest first.
+    pub fn search_fuzzy(&self, query: &str, max: f32) -> Vec<(GlossRef, f32)> {
+        let tokens = tokenize(query);
+        if tokens.is_empty() {
+            return Vec::new();
+        }
+
+        let mut totals: Option<HashMap<GlossRef, f32>> = None;
+        for token in &tokens {
+            let token = Folded::new(token);
+            let mut best: HashMap<GlossRef, f32> = HashMap::new();
+            for posting in self.postings.values() {
+                if let Some(d) = fuzzy::folded_distance_within(&token, &posting.folded, max) {
+                    for &r in &posting.refs {
+                        let entry = best.entry(r).or_insert(d);
+                        *entry = entry.min(d);
+                    }
+                }
+            }
+
+            totals = Some(match totals {
+                None => best,
+                Some(totals) => totals
+                    .into_iter()
+                    .filter_map(|(r, d)| best.get(&r).map(|b| (r, d + b)))
+                    .collect(),
+            });
+        }
+
+        let mut hits: Vec<_> = totals
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|&(_, d)| d <= max)
+            .collect();
+        hits.sort_by(|(a, da), (b, db)| da.total_cmp(db).then(a.cmp(b)));
+        hits
+    }
+
+    /// Indexed words closest to `word` within `max`, excluding the word
+    /// itself, closest first.
+    pub fn similar_words(&self, word: &str, max: f32) -> Vec<(&str, f32)> {
+        let words = self.postings.iter().map(|(w, p)| (w.as_str(), &p.folded));
+        fuzzy::nearest_folded(words, word, max)
+    }
+
+    pub fn contains_word(&self, word: &str) -> bool {
+        self.postings.contains_key(word)
+    }
+}
+
+// Ranks exact matches of the whole gloss first, then glosses where the
+// query makes up more of the text. Earlier senses and glosses and common
+// or frequent entries are preferred.
+fn score(entries: &[Entry], (e, s, g): GlossRef, query: &str, query_tokens: usize) -> i32 {
+    let entry = &entries[e];
+    let content = entry.sense[s].gloss[g].content.as_deref().unwrap_or("");
+    let gloss = normalize(content);
+
+    let mut score = if gloss == query {
+        1000
+    } else {
+        let words = tokenize(content).len().max(1);
+        let coverage = 400 * query_tokens.min(words) / words;
+        coverage as i32 + if gloss.starts_with(query) { 100 } else { 0 }
+    };
+
+    score -= 20 * s.min(10) as i32 + 5 * g.min(10) as i32;
+    if entry.is_common() {
+        score += 100;
+    }
+    if let Some(rank) = entry.frequency_rank() {
+        score += 48 - rank.min(48) as i32;
+    }
+    score
+}
+
+/// Lowercases a gloss and removes the "to " that marks verbs, so that
+/// "to eat" and "Eat" compare equal.
+pub(crate) fn normalize(gloss: &str) -> String {
+    let gloss = gloss.trim().to_lowercase();
+    let gloss = gloss.strip_prefix("to ").unwrap_or(&gloss);
+    gloss.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Splits a gloss into lowercase words, ignoring punctuation other than
+/// apostrophes and hyphens within words.
+pub(crate) fn tokenize(gloss: &str) -> Vec<String> {
+    normalize(gloss)
+        .split(|c: char| !(c.is_alphanumeric() || c == '\'' || c == '-'))
+        .map(|w| w.trim_matches(|c| c == '\'' || c == '-'))
+        .filter(|w| !w.is_empty())
+        .map(str::to_owned)
+        .collect()
+}
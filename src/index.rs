@@ -0,0 +1,496 @@
+use crate::deinflect::{Deinflection, Deinflector};
+use crate::fuzzy::{self, Folded};
+use crate::gloss::{self, GlossIndex};
+use crate::jmdict::{self, Gloss, JMDict, Sense, XRef};
+use crate::kana;
+use crate::kanjidic::{self, Kanjidic};
+use crate::pattern::FormIndex;
+use crate::trie::Trie;
+use std::cmp::Reverse;
+use std::collections::{HashMap, HashSet};
+use std::sync::OnceLock;
+
+/// A [`JMDict`] with hash indexes for constant-time lookup by sequence
+/// number and by exact kanji or reading text, a sorted index and a trie of
+/// both for pattern search and scanning text, and a word index of English
+/// glosses.
+///
+/// Only the hash indexes are built up front. The others are built the
+/// first time a search needs them.
+#[derive(Debug)]
+pub struct IndexedJMDict {
+    dict: JMDict,
+    by_seq: HashMap<u32, usize>,
+    by_kanji: HashMap<String, Vec<usize>>,
+    by_reading: HashMap<String, Vec<usize>>,
+    forms: OnceLock<FormIndex>,
+    trie: OnceLock<Trie>,
+    glosses: OnceLock<GlossIndex>,
+    deinflector: OnceLock<Deinflector>,
+    // Every reading with its folded form, for fuzzy search.
+    folded_readings: OnceLock<Vec<(String, Folded)>>,
+}
+
+/// A gloss matching an English search, with the indices of its sense and
+/// of the gloss within the sense.
+#[derive(Debug)]
+pub struct GlossHit<'a> {
+    pub entry: &'a jmdict::Entry,
+    pub sense: usize,
+    pub gloss: usize,
+    // Higher is better; only meaningful relative to other hits.
+    pub score: i32,
+}
+
+/// An entry whose form, possibly inflected, starts at the scanned
+/// position.
+#[derive(Debug)]
+pub struct ScanMatch<'a> {
+    pub entry: &'a jmdict::Entry,
+    // Length of the matched text in bytes.
+    pub len: usize,
+    // How the matched text leads back to the form of the entry; without
+    // reasons for forms matched as written.
+    pub deinflection: Deinflection,
+}
+
+/// An entry found by fuzzy search, with the reading or gloss that matched
+/// and its edit distance from the query.
+#[derive(Debug)]
+pub struct FuzzyHit<'a> {
+    pub entry: &'a jmdict::Entry,
+    pub text: &'a str,
+    pub distance: f32,
+}
+
+impl<'a> GlossHit<'a> {
+    pub fn gloss(&self) -> &'a Gloss {
+        &self.entry.sense[self.sense].gloss[self.gloss]
+    }
+}
+
+impl JMDict {
+    pub fn build_index(self) -> IndexedJMDict {
+        IndexedJMDict::new(self)
+    }
+}
+
+impl IndexedJMDict {
+    pub fn new(dict: JMDict) -> Self {
+        let mut by_seq = HashMap::new();
+        let mut by_kanji: HashMap<_, Vec<_>> = HashMap::new();
+        let mut by_reading: HashMap<_, Vec<_>> = HashMap::new();
+
+        for (i, e) in dict.entries.iter().enumerate() {
+            by_seq.insert(e.seq, i);
+            for k in &e.kanji {
+                push_unique(by_kanji.entry(k.text.clone()).or_default(), i);
+            }
+            for r in &e.reading {
+                push_unique(by_reading.entry(r.text.clone()).or_default(), i);
+            }
+        }
+
+        IndexedJMDict {
+            dict,
+            by_seq,
+            by_kanji,
+            by_reading,
+            forms: OnceLock::new(),
+            trie: OnceLock::new(),
+            glosses: OnceLock::new(),
+            deinflector: OnceLock::new(),
+            folded_readings: OnceLock::new(),
+        }
+    }
+
+    fn forms(&self) -> &FormIndex {
+        self.forms.get_or_init(|| FormIndex::new(&self.by_text()))
+    }
+
+    fn trie(&self) -> &Trie {
+        self.trie.get_or_init(|| {
+            let mut trie = Trie::new();
+            for (text, indices) in &self.by_text() {
+                for &i in indices {
+                    trie.insert(text, i);
+                }
+            }
+            trie
+        })
+    }
+
+    fn glosses(&self) -> &GlossIndex {
+        self.glosses
+            .get_or_init(|| GlossIndex::new(&self.dict.entries))
+    }
+
+    fn deinflector(&self) -> &Deinflector {
+        self.deinflector.get_or_init(Deinflector::new)
+    }
+
+    fn folded_readings(&self) -> &[(String, Folded)] {
+        self.folded_readings.get_or_init(|| {
+            self.by_reading
+                .keys()
+                .map(|r| (r.clone(), Folded::new(r)))
+                .collect()
+        })
+    }
+
+    // Indices of the entries with each kanji or reading text.
+    fn by_text(&self) -> HashMap<String, Vec<usize>> {
+        let mut by_text = self.by_kanji.clone();
+        for (text, indices) in &self.by_reading {
+            let merged = by_text.entry(text.clone()).or_default();
+            merged.extend(indices);
+            merged.sort_unstable();
+            merged.dedup();
+        }
+        by_text
+    }
+
+    pub fn dict(&self) -> &JMDict {
+        &self.dict
+    }
+
+    pub fn into_inner(self) -> JMDict {
+        self.dict
+    }
+
+    pub fn find_seq(&self, seq: u32) -> Option<&jmdict::Entry> {
+        self.by_seq.get(&seq).map(|&i| &self.dict.entries[i])
+    }
+
+    pub fn find_kanji(&self, text: &str) -> Vec<&jmdict::Entry> {
+        self.entries_at(self.by_kanji.get(text))
+    }
+
+    pub fn find_reading(&self, text: &str) -> Vec<&jmdict::Entry> {
+        self.entries_at(self.by_reading.get(text))
+    }
+
+    /// Indexed equivalent of [`JMDict::search`], returning entries in
+    /// dictionary order.
+    pub fn search(&self, phrase: &str) -> Vec<&jmdict::Entry> {
+        let mut indices: Vec<_> = self
+            .by_kanji
+            .get(phrase)
+            .into_iter()
+            .chain(self.by_reading.get(phrase))
+            .flatten()
+            .copied()
+            .collect();
+        indices.sort_unstable();
+        indices.dedup();
+
+        indices.into_iter().map(|i| &self.dict.entries[i]).collect()
+    }
+
+    /// Entries with a kanji or reading starting with `prefix`, e.g. "食べ".
+    pub fn search_prefix(&self, prefix: &str) -> Vec<&jmdict::Entry> {
+        self.entries_in(self.forms().prefix(prefix))
+    }
+
+    /// Entries with a kanji or reading ending with `suffix`, e.g. "的".
+    pub fn search_suffix(&self, suffix: &str) -> Vec<&jmdict::Entry> {
+        self.entries_in(self.forms().suffix(suffix))
+    }
+
+    /// Entries with a kanji or reading matching a wildcard pattern, where
+    /// `?` matches one character and `*` any number, e.g. "食べ*", "*的"
+    /// or "?ける".
+    pub fn search_pattern(&self, pattern: &str) -> Vec<&jmdict::Entry> {
+        self.entries_in(self.forms().glob(pattern))
+    }
+
+    /// Entries with a kanji or reading matching the regular expression
+    /// `pattern`. Only the distinct form texts are tested, and anchored
+    /// expressions with a literal prefix only test forms with that prefix.
+    /// Flags such as case insensitivity are written inline, e.g. `(?i)`.
+    pub fn search_regex(&self, pattern: &str) -> Result<Vec<&jmdict::Entry>, regex::Error> {
+        Ok(self.entries_in(self.forms().regex(pattern)?))
+    }
+
+    /// English glosses containing every word of `query`, best matches
+    /// first. Case and a leading "to " are ignored, so "to eat" ranks the
+    /// gloss "to eat" above "to eat breakfast".
+    pub fn search_gloss(&self, query: &str) -> Vec<GlossHit<'_>> {
+        self.glosses()
+            .search(&self.dict.entries, query)
+            .into_iter()
+            .map(|((e, sense, gloss), score)| GlossHit {
+                entry: &self.dict.entries[e],
+                sense,
+                gloss,
+                score,
+            })
+            .collect()
+    }
+
+    /// Entries with a reading, for kana queries, or English gloss words
+    /// otherwise, within `max_distance` edits of `query`, closest first.
+    /// See [`fuzzy`] for the cost of each edit.
+    pub fn search_fuzzy(&self, query: &str, max_distance: f32) -> Vec<FuzzyHit<'_>> {
+        let mut hits: Vec<(usize, &str, f32)> = Vec::new();
+
+        if kana::is_all_kana(query) {
+            let query = Folded::new(query);
+            for (reading, folded) in self.folded_readings() {
+                if let Some(d) = fuzzy::folded_distance_within(&query, folded, max_distance) {
+                    let indices = &self.by_reading[reading];
+                    hits.extend(indices.iter().map(|&i| (i, reading.as_str(), d)));
+                }
+            }
+        } else {
+            for ((e, s, g), d) in self.glosses().search_fuzzy(query, max_distance) {
+                let gloss = &self.dict.entries[e].sense[s].gloss[g];
+                hits.push((e, gloss.content.as_deref().unwrap_or(""), d));
+            }
+        }
+
+        hits.sort_by(|a, b| a.2.total_cmp(&b.2).then(a.0.cmp(&b.0)));
+        let mut seen = HashSet::new();
+        hits.retain(|&(i, ..)| seen.insert(i));
+
+        hits.into_iter()
+            .map(|(i, text, distance)| FuzzyHit {
+                entry: &self.dict.entries[i],
+                text,
+                distance,
+            })
+            .collect()
+    }
+
+    /// "Did you mean" suggestions for a query: the closest readings or
+    /// gloss words other than the query itself. For queries of several
+    /// words, suggests the query with each unknown word corrected.
+    pub fn suggest(&self, query: &str, limit: usize) -> Vec<String> {
+        let max = |q: &str| (q.chars().count() as f32 / 3.0).max(1.0);
+
+        if kana::is_all_kana(query) {
+            let readings = self.folded_readings().iter().map(|(r, f)| (r.as_str(), f));
+            return fuzzy::nearest_folded(readings, query, max(query))
+                .into_iter()
+                .take(limit)
+                .map(|(r, _)| r.to_owned())
+                .collect();
+        }
+
+        let words = gloss::tokenize(query);
+        if let [word] = words.as_slice() {
+            return self
+                .glosses()
+                .similar_words(word, max(word))
+                .into_iter()
+                .take(limit)
+                .map(|(w, _)| w.to_owned())
+                .collect();
+        }
+
+        let corrected: Vec<_> = words
+            .iter()
+            .map(|w| {
+                if self.glosses().contains_word(w) {
+                    return w.as_str();
+                }
+                let similar = self.glosses().similar_words(w, max(w));
+                similar.first().map_or(w.as_str(), |&(s, _)| s)
+            })
+            .collect();
+        if corrected.iter().eq(words.iter()) || limit == 0 {
+            Vec::new()
+        } else {
+            vec![corrected.join(" ")]
+        }
+    }
+
+    /// Indexed equivalent of [`JMDict::search_deinflected`].
+    pub fn search_deinflected(&self, word: &str) -> Vec<(&jmdict::Entry, Deinflection)> {
+        let mut seen = HashSet::new();
+        let mut matches = Vec::new();
+
+        for d in self.deinflector().deinflect(word) {
+            for term in d.lookup_terms() {
+                let indices = self
+                    .by_kanji
+                    .get(term)
+                    .into_iter()
+                    .chain(self.by_reading.get(term));
+                for &i in indices.flatten() {
+                    let e = &self.dict.entries[i];
+                    if d.matches(e) && seen.insert(i) {
+                        matches.push((e, d.clone()));
+                    }
+                }
+            }
+        }
+
+        matches
+    }
+
+    /// Entries whose kanji or reading, or an inflection of them, starts at
+    /// byte `offset` of `text`, longest matches first. Forms matched as
+    /// written come before deinflected ones of the same length. Returns
+    /// nothing if `offset` is not on a character boundary.
+    pub fn scan(&self, text: &str, offset: usize) -> Vec<ScanMatch<'_>> {
+        let mut matches = self.scan_all(text, offset);
+        let mut seen = HashSet::new();
+        matches.retain(|m| seen.insert(m.entry.seq));
+        matches
+    }
+
+    /// Like [`IndexedJMDict::scan`], but keeps every length an entry
+    /// matches at, once each, so that e.g. both 今日 and 今日は are found
+    /// in "今日は". The tokenizer needs all of them as edges.
+    pub(crate) fn scan_all(&self, text: &str, offset: usize) -> Vec<ScanMatch<'_>> {
+        let text = match text.get(offset..) {
+            Some(text) => text,
+            None => return Vec::new(),
+        };
+        let mut matches: Vec<ScanMatch> = Vec::new();
+
+        let (prefixes, depth) = self.trie().prefixes(text);
+        for (len, indices) in prefixes {
+            for &i in indices {
+                matches.push(ScanMatch {
+                    entry: &self.dict.entries[i],
+                    len,
+                    deinflection: Deinflection {
+                        term: text[..len].to_owned(),
+                        reasons: Vec::new(),
+                        class: None,
+                    },
+                });
+            }
+        }
+
+        // An inflected word keeps at least the first character of its
+        // dictionary form, so only text that follows a path in the trie
+        // for at least one character, plus an inflected ending, can match.
+        if depth > 0 {
+            let ends = text
+                .char_indices()
+                .map(|(i, c)| i + c.len_utf8())
+                .take(depth + MAX_ENDING_LEN);
+            for len in ends {
+                for (entry, d) in self.search_deinflected(&text[..len]) {
+                    if !d.reasons.is_empty() {
+                        matches.push(ScanMatch {
+                            entry,
+                            len,
+                            deinflection: d,
+                        });
+                    }
+                }
+            }
+        }
+
+        matches.sort_by_key(|m| (Reverse(m.len), !m.deinflection.reasons.is_empty()));
+        let mut seen = HashSet::new();
+        matches.retain(|m| seen.insert((m.entry.seq, m.len)));
+        matches
+    }
+
+    pub fn antonyms(&self, entry: &jmdict::Entry) -> Vec<(&jmdict::Entry, &Sense)> {
+        let ant = entry.sense.iter().flat_map(|s| &s.antonyms);
+        ant.flat_map(|a| self.resolve_xref(a)).collect()
+    }
+
+    pub fn cross_refs(&self, entry: &jmdict::Entry) -> Vec<(&jmdict::Entry, &Sense)> {
+        let xrefs = entry.sense.iter().flat_map(|s| &s.cross_refs);
+        xrefs.flat_map(|x| self.resolve_xref(x)).collect()
+    }
+
+    /// Indexed equivalent of [`JMDict::resolve_xref`].
+    pub fn resolve_xref(&self, xref: &XRef) -> Vec<(&jmdict::Entry, &Sense)> {
+        let candidates = match (&xref.kanji, &xref.reading) {
+            (Some(kanji), _) => self.find_kanji(kanji),
+            (None, Some(reading)) => self.find_reading(reading),
+            (None, None) => Vec::new(),
+        };
+
+        candidates
+            .into_iter()
+            .flat_map(|e| xref.senses(e).into_iter().map(move |s| (e, s)))
+            .collect()
+    }
+
+    fn entries_in(&self, indices: Vec<usize>) -> Vec<&jmdict::Entry> {
+        indices.into_iter().map(|i| &self.dict.entries[i]).collect()
+    }
+
+    fn entries_at(&self, indices: Option<&Vec<usize>>) -> Vec<&jmdict::Entry> {
+        indices
+            .into_iter()
+            .flatten()
+            .map(|&i| &self.dict.entries[i])
+            .collect()
+    }
+}
+
+// Length in characters of the longest inflected ending [`IndexedJMDict::scan`]
+// looks for, e.g. させられませんでした.
+const MAX_ENDING_LEN: usize = 10;
+
+/// A [`Kanjidic`] with hash indexes for constant-time lookup by literal and
+/// by codepoint.
+#[derive(Debug)]
+pub struct IndexedKanjidic {
+    dict: Kanjidic,
+    by_literal: HashMap<String, usize>,
+    by_codepoint: HashMap<(String, String), usize>,
+}
+
+impl Kanjidic {
+    pub fn build_index(self) -> IndexedKanjidic {
+        IndexedKanjidic::new(self)
+    }
+}
+
+impl IndexedKanjidic {
+    pub fn new(dict: Kanjidic) -> Self {
+        let mut by_literal = HashMap::new();
+        let mut by_codepoint = HashMap::new();
+
+        for (i, e) in dict.entries.iter().enumerate() {
+            by_literal.entry(e.literal.clone()).or_insert(i);
+            for cp in &e.codepoints {
+                by_codepoint
+                    .entry((cp.standard.clone(), cp.value.to_lowercase()))
+                    .or_insert(i);
+            }
+        }
+
+        IndexedKanjidic {
+            dict,
+            by_literal,
+            by_codepoint,
+        }
+    }
+
+    pub fn dict(&self) -> &Kanjidic {
+        &self.dict
+    }
+
+    pub fn into_inner(self) -> Kanjidic {
+        self.dict
+    }
+
+    pub fn find_literal(&self, literal: &str) -> Option<&kanjidic::Entry> {
+        self.by_literal.get(literal).map(|&i| &self.dict.entries[i])
+    }
+
+    /// Looks up a character by one of its `<cp_value>` codes, e.g.
+    /// `("ucs", "65e5")` or `("jis208", "1-38-92")`.
+    pub fn find_codepoint(&self, standard: &str, value: &str) -> Option<&kanjidic::Entry> {
+        let key = (standard.to_owned(), value.to_lowercase());
+        self.by_codepoint.get(&key).map(|&i| &self.dict.entries[i])
+    }
+}
+
+fn push_unique(indices: &mut Vec<usize>, i: usize) {
+    if indices.last() != Some(&i) {
+        indices.push(i);
+    }
+}
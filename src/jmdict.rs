@@ -1,13 +1,29 @@
+use crate::deinflect::Deinflection;
+use crate::entities::{Dialect, Field, KanjiInfo, Misc, PartOfSpeech, ReadingInfo};
 use crate::errors::{ParseEnumError, ParseError, XmlError};
-use crate::util::{self, find_child_tag, find_child_tag_err, get_node_text, ns_xml_attr};
+use crate::kana::{self, NormalizeOptions};
+use crate::language::Language;
+use crate::romaji;
+use crate::search;
+use crate::stream::ElementReader;
+use crate::util::{find_child_tag, find_child_tag_err, get_node_text, ns_xml_attr};
 use roxmltree::{Document, Node};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::convert::Infallible;
+use std::fmt;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::str::FromStr;
 
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct JMDict {
     pub entries: Vec<Entry>,
 }
 
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Entry {
     pub seq: u32,
     pub reading: Vec<Reading>,
@@ -16,20 +32,26 @@ pub struct Entry {
 }
 
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Kanji {
     pub text: String,
-    pub pri_ref: Option<PriRef>,
+    pub info: Vec<KanjiInfo>,
+    pub pri_ref: Vec<PriRef>,
 }
 
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Reading {
     pub text: String,
-    pub pri_ref: Option<PriRef>,
+    // Set by <re_nokanji>: the reading cannot be regarded as a true reading
+    // of any of the kanji elements, e.g. foreign words written in katakana.
+    pub no_kanji: bool,
+    pub pri_ref: Vec<PriRef>,
     pub restrict: Vec<String>,
-    pub inf: Option<String>,
+    pub info: Vec<ReadingInfo>,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum PriRef {
     News1,
     News2,
@@ -43,32 +65,56 @@ pub enum PriRef {
 }
 
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Sense {
     pub restrict_reading: Vec<String>,
     pub restrict_kanji: Vec<String>,
-    pub cross_refs: Vec<String>,
+    pub cross_refs: Vec<XRef>,
     pub gloss: Vec<Gloss>,
-    pub antonyms: Vec<String>,
-    pub pos: Vec<String>,
-    pub fields: Vec<String>,
-    pub misc: Vec<String>,
+    pub antonyms: Vec<XRef>,
+    pub pos: Vec<PartOfSpeech>,
+    pub fields: Vec<Field>,
+    pub misc: Vec<Misc>,
     pub source_lang: Vec<LSource>,
-    pub dialects: Vec<String>,
+    pub dialects: Vec<Dialect>,
     pub info: Vec<String>,
 }
 
+/// A written form of an entry: a reading, the kanji it is written with, if
+/// any, and the senses that apply to that combination.
+#[derive(Debug)]
+pub struct Form<'a> {
+    pub kanji: Option<&'a Kanji>,
+    pub reading: &'a Reading,
+    pub senses: Vec<&'a Sense>,
+}
+
+/// Reference to another entry, and optionally one of its senses, as used by
+/// `<xref>` and `<ant>`. The XML form joins the parts with `・`, e.g.
+/// "漢字・かんじ・2", where any of the parts may be absent.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct XRef {
+    pub kanji: Option<String>,
+    pub reading: Option<String>,
+    // Zero-based, while the XML numbers senses starting from 1.
+    pub sense_index: Option<usize>,
+}
+
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Gloss {
     pub content: Option<String>,
-    pub lang: String,
+    pub lang: Language,
     pub gender: Option<String>,
+    #[cfg_attr(feature = "serde", serde(rename = "type"))]
     pub typ: Option<String>,
 }
 
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct LSource {
     pub content: Option<String>,
-    pub lang: String,
+    pub lang: Language,
     // The full attribute indicates whether the source language
     // fully or partially describes the source word or phrase of the
     // loanword. If absent, it will have the implied value of "full".
@@ -98,7 +144,7 @@ impl JMDict {
     {
         self.entries
             .iter()
-            .filter(|e| e.reading.iter().any(|r| predicate(r)))
+            .filter(|e| e.reading.iter().any(&predicate))
             .collect()
     }
 
@@ -108,7 +154,7 @@ impl JMDict {
     {
         self.entries
             .iter()
-            .filter(|e| e.kanji.iter().any(|k| predicate(k)))
+            .filter(|e| e.kanji.iter().any(&predicate))
             .collect()
     }
 
@@ -118,7 +164,15 @@ impl JMDict {
     {
         self.entries
             .iter()
-            .filter(|e| e.sense.iter().flat_map(|s| &s.gloss).any(|g| predicate(g)))
+            .filter(|e| e.sense.iter().flat_map(|s| &s.gloss).any(&predicate))
+            .collect()
+    }
+
+    /// Entries with at least one sense glossed in `language`.
+    pub fn filter_language(&self, language: Language) -> Vec<&Entry> {
+        self.entries
+            .iter()
+            .filter(|e| !e.senses_in(language).is_empty())
             .collect()
     }
 
@@ -132,36 +186,411 @@ impl JMDict {
             .collect()
     }
 
-    pub fn antonyms(&self, entry: &Entry) -> Vec<&Entry> {
+    /// Like [`JMDict::search`], but compares kana after normalizing both the
+    /// phrase and the dictionary text, so that e.g. "コーヒー" and "こーひー"
+    /// find the same entries.
+    pub fn search_normalized(&self, phrase: &str, options: &NormalizeOptions) -> Vec<&Entry> {
+        search::normalized(self.entries.iter(), phrase, options)
+    }
+
+    /// Finds entries with a reading matching romaji input in any of the
+    /// supported romanization systems, e.g. "taberu", "kyou" or "kōhī".
+    /// Long vowel marks in readings are expanded before comparison so that
+    /// "koohii" also finds "コーヒー".
+    pub fn search_romaji(&self, romaji: &str) -> Vec<&Entry> {
+        search::romaji(self.entries.iter(), romaji)
+    }
+
+    /// Finds the dictionary forms of a possibly inflected word, e.g.
+    /// 食べる for "食べなかった". Each entry is returned once, with the
+    /// shortest deinflection that leads to it.
+    pub fn search_deinflected(&self, word: &str) -> Vec<(&Entry, Deinflection)> {
+        search::deinflected(self.entries.iter(), word)
+    }
+
+    pub fn antonyms(&self, entry: &Entry) -> Vec<(&Entry, &Sense)> {
         let ant = entry.sense.iter().flat_map(|s| &s.antonyms);
-        ant.flat_map(|a| self.search(&a)).collect()
+        ant.flat_map(|a| self.resolve_xref(a)).collect()
+    }
+
+    pub fn cross_refs(&self, entry: &Entry) -> Vec<(&Entry, &Sense)> {
+        let xrefs = entry.sense.iter().flat_map(|s| &s.cross_refs);
+        xrefs.flat_map(|x| self.resolve_xref(x)).collect()
+    }
+
+    /// Returns the senses targeted by a cross-reference; every sense of the
+    /// matching entries if the reference does not name one.
+    pub fn resolve_xref(&self, xref: &XRef) -> Vec<(&Entry, &Sense)> {
+        self.entries
+            .iter()
+            .flat_map(|e| xref.senses(e).into_iter().map(move |s| (e, s)))
+            .collect()
+    }
+}
+
+impl Entry {
+    /// Whether any kanji or reading element of the entry is marked common.
+    pub fn is_common(&self) -> bool {
+        self.kanji.iter().any(|k| k.is_common()) || self.reading.iter().any(|r| r.is_common())
+    }
+
+    /// The best `nfXX` frequency band among the kanji and reading elements.
+    pub fn frequency_rank(&self) -> Option<u32> {
+        let kanji = self.kanji.iter().filter_map(|k| k.frequency_rank());
+        let reading = self.reading.iter().filter_map(|r| r.frequency_rank());
+        kanji.chain(reading).min()
+    }
+
+    /// Returns every valid pairing of kanji and reading elements, honouring
+    /// `<re_restr>` and `<re_nokanji>`, along with the senses whose
+    /// `<stagk>`/`<stagr>` restrictions allow that pairing. Readings only
+    /// appear without kanji if they are marked `<re_nokanji>` or the entry
+    /// has no kanji elements.
+    pub fn forms(&self) -> Vec<Form<'_>> {
+        let mut forms = Vec::new();
+
+        for k in &self.kanji {
+            for r in self.reading.iter().filter(|r| r.applies_to(k)) {
+                forms.push(self.form(Some(k), r));
+            }
+        }
+
+        for r in &self.reading {
+            if r.no_kanji || self.kanji.is_empty() {
+                forms.push(self.form(None, r));
+            }
+        }
+
+        forms
+    }
+
+    /// Senses with a gloss in `language`. In the multilingual JMdict, each
+    /// language has its own senses.
+    pub fn senses_in(&self, language: Language) -> Vec<&Sense> {
+        self.sense
+            .iter()
+            .filter(|s| s.gloss.iter().any(|g| g.lang == language))
+            .collect()
+    }
+
+    fn form<'a>(&'a self, kanji: Option<&'a Kanji>, reading: &'a Reading) -> Form<'a> {
+        let senses = self
+            .sense
+            .iter()
+            .filter(|s| s.applies_to(kanji, reading))
+            .collect();
+
+        Form {
+            kanji,
+            reading,
+            senses,
+        }
+    }
+}
+
+impl Kanji {
+    pub fn is_search_only(&self) -> bool {
+        self.info.contains(&KanjiInfo::SearchOnlyKanji)
+    }
+
+    pub fn is_irregular(&self) -> bool {
+        self.info.iter().any(is_irregular_kanji)
+    }
+
+    pub fn is_common(&self) -> bool {
+        self.pri_ref.iter().any(|p| p.is_common())
+    }
+
+    pub fn frequency_rank(&self) -> Option<u32> {
+        frequency_rank(&self.pri_ref)
+    }
+}
+
+pub(crate) fn is_irregular_kanji(info: &KanjiInfo) -> bool {
+    matches!(
+        info,
+        KanjiInfo::IrregularKana | KanjiInfo::IrregularKanji | KanjiInfo::IrregularOkurigana
+    )
+}
+
+impl Reading {
+    /// Whether the reading may be used with the given kanji element.
+    pub fn applies_to(&self, kanji: &Kanji) -> bool {
+        !self.no_kanji && (self.restrict.is_empty() || self.restrict.contains(&kanji.text))
+    }
+
+    pub fn is_search_only(&self) -> bool {
+        self.info.contains(&ReadingInfo::SearchOnlyKana)
+    }
+
+    pub fn is_irregular(&self) -> bool {
+        self.info.contains(&ReadingInfo::IrregularKana)
+    }
+
+    pub fn is_common(&self) -> bool {
+        self.pri_ref.iter().any(|p| p.is_common())
+    }
+
+    pub fn frequency_rank(&self) -> Option<u32> {
+        frequency_rank(&self.pri_ref)
+    }
+
+    pub fn romanize(&self, options: &romaji::RomanizeOptions) -> String {
+        romaji::romanize(&self.text, options)
+    }
+}
+
+impl Sense {
+    pub fn glosses_in(&self, language: Language) -> Vec<&Gloss> {
+        self.gloss.iter().filter(|g| g.lang == language).collect()
+    }
+
+    /// Whether the sense applies to the form written with the given kanji
+    /// element, or with the reading alone if `kanji` is `None`.
+    pub fn applies_to(&self, kanji: Option<&Kanji>, reading: &Reading) -> bool {
+        let kanji_allowed = match kanji {
+            Some(k) => self.restrict_kanji.is_empty() || self.restrict_kanji.contains(&k.text),
+            None => self.restrict_kanji.is_empty(),
+        };
+        let reading_allowed =
+            self.restrict_reading.is_empty() || self.restrict_reading.contains(&reading.text);
+
+        kanji_allowed && reading_allowed
+    }
+}
+
+impl PriRef {
+    /// Whether the tag marks a common word. As on jisho.org and WWWJDIC,
+    /// these are news1, ichi1, spec1, spec2 and gai1; the second-tier lists
+    /// and the `nfXX` bands alone do not count.
+    pub fn is_common(&self) -> bool {
+        matches!(
+            self,
+            PriRef::News1 | PriRef::Ichi1 | PriRef::Spec1 | PriRef::Spec2 | PriRef::Gai1
+        )
     }
 }
 
+// The nfXX bands rank the newspaper word frequency list in sets of 500 words,
+// nf01 holding the most frequent, so lower values sort first.
+fn frequency_rank(pri_refs: &[PriRef]) -> Option<u32> {
+    pri_refs
+        .iter()
+        .filter_map(|p| match p {
+            PriRef::NF(n) => Some(*n),
+            _ => None,
+        })
+        .min()
+}
+
+/// Written as in `<ke_pri>` and `<re_pri>`, e.g. "news1" or "nf12".
+impl fmt::Display for PriRef {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PriRef::News1 => write!(f, "news1"),
+            PriRef::News2 => write!(f, "news2"),
+            PriRef::Ichi1 => write!(f, "ichi1"),
+            PriRef::Ichi2 => write!(f, "ichi2"),
+            PriRef::Spec1 => write!(f, "spec1"),
+            PriRef::Spec2 => write!(f, "spec2"),
+            PriRef::Gai1 => write!(f, "gai1"),
+            PriRef::Gai2 => write!(f, "gai2"),
+            PriRef::NF(n) => write!(f, "nf{:02}", n),
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl Serialize for PriRef {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> Deserialize<'de> for PriRef {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let code = String::deserialize(deserializer)?;
+        parse_pri_ref(&code).map_err(serde::de::Error::custom)
+    }
+}
+
+/// Serialized in its XML form, e.g. "漢字・かんじ・2".
+#[cfg(feature = "serde")]
+impl Serialize for XRef {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> Deserialize<'de> for XRef {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let text = String::deserialize(deserializer)?;
+        Ok(XRef::from(text.as_str()))
+    }
+}
+
+impl XRef {
+    pub fn matches(&self, entry: &Entry) -> bool {
+        let kanji_matches = match &self.kanji {
+            Some(kanji) => entry.kanji.iter().any(|k| &k.text == kanji),
+            None => true,
+        };
+        let reading_matches = match &self.reading {
+            Some(reading) => entry.reading.iter().any(|r| &r.text == reading),
+            None => true,
+        };
+
+        kanji_matches && reading_matches
+    }
+
+    /// The senses of `entry` targeted by the reference; empty if the entry
+    /// does not match.
+    pub fn senses<'a>(&self, entry: &'a Entry) -> Vec<&'a Sense> {
+        if !self.matches(entry) {
+            return Vec::new();
+        }
+
+        match self.sense_index {
+            Some(i) => entry.sense.get(i).into_iter().collect(),
+            None => entry.sense.iter().collect(),
+        }
+    }
+}
+
+impl From<&str> for XRef {
+    fn from(text: &str) -> Self {
+        let mut parts: Vec<_> = text.split(XREF_SEPARATOR).collect();
+
+        let sense_index = match parts.last().and_then(|p| p.parse::<usize>().ok()) {
+            Some(n) if parts.len() > 1 => {
+                parts.pop();
+                n.checked_sub(1)
+            }
+            _ => None,
+        };
+
+        let (kanji, reading) = match parts.as_slice() {
+            // A ・ may also be part of a headword, as in "ジョン・スミス", so
+            // only a kana part after a non-kana one is taken as its reading.
+            [kanji, reading] if !kana::is_all_kana(kanji) && kana::is_all_kana(reading) => {
+                (Some(kanji.to_string()), Some(reading.to_string()))
+            }
+            _ => {
+                let form = parts.join(XREF_SEPARATOR);
+                if kana::is_all_kana(&form) {
+                    (None, Some(form))
+                } else {
+                    (Some(form), None)
+                }
+            }
+        };
+
+        XRef {
+            kanji,
+            reading,
+            sense_index,
+        }
+    }
+}
+
+impl fmt::Display for XRef {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let sense = self.sense_index.map(|i| (i + 1).to_string());
+        let parts: Vec<_> = [&self.kanji, &self.reading, &sense]
+            .iter()
+            .filter_map(|p| p.as_deref())
+            .collect();
+        write!(f, "{}", parts.join(XREF_SEPARATOR))
+    }
+}
+
+const XREF_SEPARATOR: &str = "・";
+
 impl JMDict {
     pub fn from_file(filepath: &str) -> Result<Self, ParseError> {
-        let contents = util::read_file(filepath)?;
-        let doc = Document::parse(&contents).map_err(XmlError::Roxml)?;
-
-        let entries: Vec<_> = doc
-            .root_element()
-            .children()
-            .filter(|n| n.is_element())
-            .map(|n| parse_entry(n))
+        JMDict::from_file_with_options(filepath, ParseOptions::default())
+    }
+
+    pub fn from_file_with_options(
+        filepath: &str,
+        options: ParseOptions,
+    ) -> Result<Self, ParseError> {
+        let file = File::open(filepath)?;
+        let entries = JMDict::stream_with_options(BufReader::new(file), options)
             .collect::<Result<Vec<_>, _>>()?;
 
         Ok(JMDict { entries })
     }
+
+    /// Lazily parses entries from a JMdict XML document, holding only one
+    /// `<entry>` element in memory at a time.
+    pub fn stream<R: BufRead>(reader: R) -> EntryStream<R> {
+        JMDict::stream_with_options(reader, ParseOptions::default())
+    }
+
+    pub fn stream_with_options<R: BufRead>(reader: R, options: ParseOptions) -> EntryStream<R> {
+        EntryStream {
+            elements: ElementReader::new(reader).keep_entity_names(),
+            options,
+        }
+    }
+}
+
+/// Normalization applied to each entry as it is parsed.
+#[derive(Debug, Clone)]
+pub struct ParseOptions {
+    // A sense without <pos> takes the parts of speech of the preceding
+    // sense, as described by the DTD. Enabled by default.
+    pub inherit_pos: bool,
+    // The DTD only notes that <misc> information "will usually apply to
+    // several senses", so copying it forward is opt-in.
+    pub inherit_misc: bool,
+}
+
+impl Default for ParseOptions {
+    fn default() -> Self {
+        ParseOptions {
+            inherit_pos: true,
+            inherit_misc: false,
+        }
+    }
+}
+
+/// Iterator over the entries of a JMdict document, created by
+/// [`JMDict::stream`].
+pub struct EntryStream<R> {
+    elements: ElementReader<R>,
+    options: ParseOptions,
+}
+
+impl<R: BufRead> Iterator for EntryStream<R> {
+    type Item = Result<Entry, ParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.elements.next_element(ENTRY) {
+            Ok(Some(xml)) => Some(parse_entry_xml(&xml, &self.options)),
+            Ok(None) => None,
+            Err(err) => Some(Err(err)),
+        }
+    }
+}
+
+fn parse_entry_xml(xml: &str, options: &ParseOptions) -> Result<Entry, ParseError> {
+    let doc = Document::parse(xml).map_err(XmlError::Roxml)?;
+    parse_entry(doc.root_element(), options)
 }
 
 const_strs!(
+    ENTRY: "entry",
     SEQ: "ent_seq",
     KANJI_ELE: "k_ele",
     READING_ELE: "r_ele",
     SENSE: "sense",
 );
 
-fn parse_entry(n: Node) -> Result<Entry, ParseError> {
+fn parse_entry(n: Node, options: &ParseOptions) -> Result<Entry, ParseError> {
     let mut reading = Vec::new();
     let mut kanji = Vec::new();
     let mut sense = Vec::new();
@@ -191,6 +620,8 @@ fn parse_entry(n: Node) -> Result<Entry, ParseError> {
         }
     }
 
+    inherit_sense_tags(&mut sense, options);
+
     Ok(Entry {
         seq,
         reading,
@@ -199,18 +630,35 @@ fn parse_entry(n: Node) -> Result<Entry, ParseError> {
     })
 }
 
+fn inherit_sense_tags(senses: &mut [Sense], options: &ParseOptions) {
+    for i in 1..senses.len() {
+        let (previous, rest) = senses.split_at_mut(i);
+        let previous = &previous[i - 1];
+        let sense = &mut rest[0];
+
+        if options.inherit_pos && sense.pos.is_empty() {
+            sense.pos = previous.pos.clone();
+        }
+        if options.inherit_misc && sense.misc.is_empty() {
+            sense.misc = previous.misc.clone();
+        }
+    }
+}
+
 const_strs!(
     READING_TEXT: "reb",
     READING_PRI: "re_pri",
+    READING_NO_KANJI: "re_nokanji",
     READING_RESTRICT: "re_restr",
     READING_INF: "re_inf"
 );
 
-fn parse_reading(n: Node) -> Result<Reading, ParseError> {
+pub(crate) fn parse_reading(n: Node) -> Result<Reading, ParseError> {
     let mut reb_op: Option<String> = None;
-    let mut re_pri: Option<PriRef> = None;
+    let mut re_pri = Vec::new();
+    let mut no_kanji = false;
     let mut restrict = Vec::new();
-    let mut inf: Option<String> = None;
+    let mut info = Vec::new();
 
     for c in n.children() {
         let tag_name = c.tag_name().name();
@@ -218,10 +666,11 @@ fn parse_reading(n: Node) -> Result<Reading, ParseError> {
             READING_TEXT => reb_op = Some(get_node_text(c)?.into_owned()),
             READING_PRI => {
                 let re_pri_text = get_node_text(c).ok();
-                re_pri = re_pri_text.and_then(|t| parse_pri_ref(t.as_ref()).ok());
+                re_pri.extend(re_pri_text.and_then(|t| parse_pri_ref(t.as_ref()).ok()));
             }
+            READING_NO_KANJI => no_kanji = true,
             READING_RESTRICT => restrict.push(get_node_text(c)?.into_owned()),
-            READING_INF => inf = Some(get_node_text(c)?.into_owned()),
+            READING_INF => info.push(parse_entity(&get_node_text(c)?)),
             _ => {}
         }
     }
@@ -230,27 +679,37 @@ fn parse_reading(n: Node) -> Result<Reading, ParseError> {
 
     Ok(Reading {
         text: reb,
+        no_kanji,
         pri_ref: re_pri,
         restrict,
-        inf,
+        info,
     })
 }
 
 const_strs!(
     KANJI_TEXT: "keb",
+    KANJI_INF: "ke_inf",
     KANJI_PRI: "ke_pri",
 );
 
-fn parse_kanji(n: Node) -> Result<Kanji, ParseError> {
+pub(crate) fn parse_kanji(n: Node) -> Result<Kanji, ParseError> {
     let keb_node = find_child_tag_err(n, KANJI_TEXT)?;
     let keb = get_node_text(keb_node)?;
 
-    let ke_pri = find_child_tag(n, KANJI_PRI)
-        .and_then(|k| k.text())
-        .and_then(|t| parse_pri_ref(t).ok());
+    let mut info = Vec::new();
+    let mut ke_pri = Vec::new();
+
+    for c in n.children() {
+        match c.tag_name().name() {
+            KANJI_INF => info.push(parse_entity(&get_node_text(c)?)),
+            KANJI_PRI => ke_pri.extend(c.text().and_then(|t| parse_pri_ref(t).ok())),
+            _ => {}
+        }
+    }
 
     Ok(Kanji {
         text: keb.into_owned(),
+        info,
         pri_ref: ke_pri,
     })
 }
@@ -327,21 +786,21 @@ fn parse_sense(n: Node) -> Result<Sense, ParseError> {
         match tag {
             RESTRICT_READING => sense.restrict_reading.push(text?.into_owned()),
             RESTRICT_KANJI => sense.restrict_kanji.push(text?.into_owned()),
-            CROSS_REF => sense.cross_refs.push(text?.into_owned()),
-            ANTONYM => sense.antonyms.push(text?.into_owned()),
-            POS => sense.pos.push(text?.into_owned()),
-            FIELD => sense.fields.push(text?.into_owned()),
-            MISC => sense.misc.push(text?.into_owned()),
-            DIALECT => sense.dialects.push(text?.into_owned()),
+            CROSS_REF => sense.cross_refs.push(XRef::from(text?.as_ref())),
+            ANTONYM => sense.antonyms.push(XRef::from(text?.as_ref())),
+            POS => sense.pos.push(parse_entity(&text?)),
+            FIELD => sense.fields.push(parse_entity(&text?)),
+            MISC => sense.misc.push(parse_entity(&text?)),
+            DIALECT => sense.dialects.push(parse_entity(&text?)),
             INFO => sense.info.push(text?.into_owned()),
             LSOURCE => {
-                let content = text.ok().and_then(|t| Some(t.into_owned()));
+                let content = text.ok().map(|t| t.into_owned());
                 let lang = c
                     .attribute(ns_xml_attr(LSOURCE_LANG_SUFFIX))
-                    .unwrap_or_else(|| LSOURCE_LANG_DEF)
-                    .to_owned();
-                let full = c.attribute(LSOURCE_TYPE).map_or(true, |_| false);
-                let wasei = c.attribute(LSOURCE_WASEI).map_or(false, |_| true);
+                    .unwrap_or(LSOURCE_LANG_DEF);
+                let lang = Language::from_iso639_2(lang)?;
+                let full = c.attribute(LSOURCE_TYPE).is_none();
+                let wasei = c.attribute(LSOURCE_WASEI).is_some();
 
                 sense.source_lang.push(LSource {
                     content,
@@ -351,13 +810,13 @@ fn parse_sense(n: Node) -> Result<Sense, ParseError> {
                 });
             }
             GLOSS => {
-                let content = text.ok().and_then(|t| Some(t.into_owned()));
+                let content = text.ok().map(|t| t.into_owned());
                 let lang = c
                     .attribute(ns_xml_attr(GLOSS_LANG_SUFFIX))
-                    .unwrap_or_else(|| GLOSS_LANG_DEFAULT)
-                    .to_owned();
-                let gender = c.attribute(GLOSS_GENDER).and_then(|g| Some(g.to_owned()));
-                let typ = c.attribute(GLOSS_TYPE).and_then(|t| Some(t.to_owned()));
+                    .unwrap_or(GLOSS_LANG_DEFAULT);
+                let lang = Language::from_iso639_2(lang)?;
+                let gender = c.attribute(GLOSS_GENDER).map(|g| g.to_owned());
+                let typ = c.attribute(GLOSS_TYPE).map(|t| t.to_owned());
                 sense.gloss.push(Gloss {
                     content,
                     lang,
@@ -371,3 +830,10 @@ fn parse_sense(n: Node) -> Result<Sense, ParseError> {
 
     Ok(sense)
 }
+
+pub(crate) fn parse_entity<T: FromStr<Err = Infallible>>(text: &str) -> T {
+    match text.parse() {
+        Ok(value) => value,
+        Err(never) => match never {},
+    }
+}
@@ -0,0 +1,257 @@
+//! JMnedict, the companion dictionary of Japanese proper names.
+//!
+//! Entries share the `<k_ele>` and `<r_ele>` elements of JMdict, so the
+//! kanji and reading types are reused, but in place of senses they have
+//! `<trans>` elements giving the kind of name and its transcriptions.
+
+use crate::entities::NameType;
+use crate::errors::{ParseError, XmlError};
+use crate::jmdict::{self, Kanji, Reading, XRef};
+use crate::kana::NormalizeOptions;
+use crate::language::Language;
+use crate::search;
+use crate::stream::ElementReader;
+use crate::util::{find_child_tag, get_node_text, ns_xml_attr};
+use roxmltree::{Document, Node};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct JMnedict {
+    pub entries: Vec<Entry>,
+}
+
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Entry {
+    pub seq: u32,
+    pub reading: Vec<Reading>,
+    pub kanji: Vec<Kanji>,
+    pub translations: Vec<Translation>,
+}
+
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Translation {
+    pub name_types: Vec<NameType>,
+    pub cross_refs: Vec<XRef>,
+    pub details: Vec<TranslationDetail>,
+}
+
+/// A transcription or translation of the name, from `<trans_det>`.
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct TranslationDetail {
+    pub content: String,
+    pub lang: Language,
+}
+
+impl JMnedict {
+    pub fn from_file(filepath: &str) -> Result<Self, ParseError> {
+        let file = File::open(filepath)?;
+        let entries = JMnedict::stream(BufReader::new(file)).collect::<Result<Vec<_>, _>>()?;
+
+        Ok(JMnedict { entries })
+    }
+
+    /// Lazily parses entries from a JMnedict XML document, holding only one
+    /// `<entry>` element in memory at a time.
+    pub fn stream<R: BufRead>(reader: R) -> EntryStream<R> {
+        EntryStream {
+            elements: ElementReader::new(reader).keep_entity_names(),
+        }
+    }
+
+    pub fn find_seq(&self, seq: u32) -> Option<&Entry> {
+        self.entries.iter().find(|e| e.seq == seq)
+    }
+
+    pub fn filter<F>(&self, predicate: F) -> Vec<&Entry>
+    where
+        F: Fn(&Entry) -> bool,
+    {
+        self.entries.iter().filter(|e| predicate(e)).collect()
+    }
+
+    pub fn filter_reading<F>(&self, predicate: F) -> Vec<&Entry>
+    where
+        F: Fn(&Reading) -> bool,
+    {
+        self.entries
+            .iter()
+            .filter(|e| e.reading.iter().any(&predicate))
+            .collect()
+    }
+
+    pub fn filter_kanji<F>(&self, predicate: F) -> Vec<&Entry>
+    where
+        F: Fn(&Kanji) -> bool,
+    {
+        self.entries
+            .iter()
+            .filter(|e| e.kanji.iter().any(&predicate))
+            .collect()
+    }
+
+    pub fn filter_translation<F>(&self, predicate: F) -> Vec<&Entry>
+    where
+        F: Fn(&TranslationDetail) -> bool,
+    {
+        self.entries
+            .iter()
+            .filter(|e| {
+                e.translations
+                    .iter()
+                    .flat_map(|t| &t.details)
+                    .any(&predicate)
+            })
+            .collect()
+    }
+
+    /// Entries with a translation of the given kind, e.g. all place names.
+    pub fn filter_name_type(&self, name_type: &NameType) -> Vec<&Entry> {
+        self.entries
+            .iter()
+            .filter(|e| e.has_name_type(name_type))
+            .collect()
+    }
+
+    pub fn search(&self, phrase: &str) -> Vec<&Entry> {
+        self.entries
+            .iter()
+            .filter(|e| {
+                e.reading.iter().any(|r| r.text == phrase)
+                    || e.kanji.iter().any(|k| k.text == phrase)
+            })
+            .collect()
+    }
+
+    /// Like [`JMnedict::search`], but compares kana after normalizing both
+    /// the phrase and the dictionary text.
+    pub fn search_normalized(&self, phrase: &str, options: &NormalizeOptions) -> Vec<&Entry> {
+        search::normalized(self.entries.iter(), phrase, options)
+    }
+
+    /// Finds entries by one of their transcriptions, ignoring case, e.g.
+    /// "koizumi" for 小泉.
+    pub fn search_translation(&self, phrase: &str) -> Vec<&Entry> {
+        let phrase = phrase.to_lowercase();
+        self.filter_translation(|d| d.content.to_lowercase() == phrase)
+    }
+}
+
+impl Entry {
+    pub fn name_types(&self) -> impl Iterator<Item = &NameType> {
+        self.translations.iter().flat_map(|t| &t.name_types)
+    }
+
+    pub fn has_name_type(&self, name_type: &NameType) -> bool {
+        self.name_types().any(|t| t == name_type)
+    }
+}
+
+/// Iterator over the entries of a JMnedict document, created by
+/// [`JMnedict::stream`].
+pub struct EntryStream<R> {
+    elements: ElementReader<R>,
+}
+
+impl<R: BufRead> Iterator for EntryStream<R> {
+    type Item = Result<Entry, ParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.elements.next_element(ENTRY) {
+            Ok(Some(xml)) => Some(parse_entry_xml(&xml)),
+            Ok(None) => None,
+            Err(err) => Some(Err(err)),
+        }
+    }
+}
+
+fn parse_entry_xml(xml: &str) -> Result<Entry, ParseError> {
+    let doc = Document::parse(xml).map_err(XmlError::Roxml)?;
+    parse_entry(doc.root_element())
+}
+
+const_strs!(
+    ENTRY: "entry",
+    SEQ: "ent_seq",
+    KANJI_ELE: "k_ele",
+    READING_ELE: "r_ele",
+    TRANS: "trans",
+);
+
+fn parse_entry(n: Node) -> Result<Entry, ParseError> {
+    let mut reading = Vec::new();
+    let mut kanji = Vec::new();
+    let mut translations = Vec::new();
+
+    let seq: u32 = {
+        let seq_text = find_child_tag(n, SEQ).and_then(|t| t.text());
+
+        match seq_text {
+            Some(t) => t.parse()?,
+            None => return Err(XmlError::MissingTag(SEQ.to_owned()).into()),
+        }
+    };
+
+    for c in n.children() {
+        match c.tag_name().name() {
+            KANJI_ELE => kanji.push(jmdict::parse_kanji(c)?),
+            READING_ELE => reading.push(jmdict::parse_reading(c)?),
+            TRANS => translations.push(parse_translation(c)?),
+            _ => {}
+        }
+    }
+
+    Ok(Entry {
+        seq,
+        reading,
+        kanji,
+        translations,
+    })
+}
+
+const_strs!(
+    NAME_TYPE: "name_type",
+    CROSS_REF: "xref",
+    TRANS_DET: "trans_det",
+    TRANS_DET_LANG_SUFFIX: "lang",
+    TRANS_DET_LANG_DEFAULT: "eng",
+);
+
+fn parse_translation(n: Node) -> Result<Translation, ParseError> {
+    let mut translation = Translation {
+        name_types: Vec::new(),
+        cross_refs: Vec::new(),
+        details: Vec::new(),
+    };
+
+    for c in n.children() {
+        match c.tag_name().name() {
+            NAME_TYPE => {
+                let text = get_node_text(c)?;
+                translation.name_types.push(jmdict::parse_entity(&text));
+            }
+            CROSS_REF => {
+                let text = get_node_text(c)?;
+                translation.cross_refs.push(XRef::from(text.as_ref()));
+            }
+            TRANS_DET => {
+                let lang = c
+                    .attribute(ns_xml_attr(TRANS_DET_LANG_SUFFIX))
+                    .unwrap_or(TRANS_DET_LANG_DEFAULT);
+                translation.details.push(TranslationDetail {
+                    content: get_node_text(c)?.into_owned(),
+                    lang: Language::from_iso639_2(lang)?,
+                });
+            }
+            _ => {}
+        }
+    }
+
+    Ok(translation)
+}
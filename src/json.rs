@@ -0,0 +1,93 @@
+//! JSON-lines export of the dictionaries, one entry per line.
+//!
+//! The JSON shape of every type follows its Rust definition, with these
+//! rules kept stable between releases:
+//!
+//! - Fields are named as in the structs, except `typ`, which is written
+//!   `type`. Every field is present: missing values are `null` and empty
+//!   lists `[]`.
+//! - Entity values (parts of speech, misc, fields, dialects, kanji and
+//!   reading info, name types) are their entity codes, e.g. `"v1"` or
+//!   `"uk"`. Codes this crate does not know are kept as they are.
+//! - Languages are ISO 639-3 codes, e.g. `"eng"` or `"deu"`.
+//! - Priority tags are written as in the XML, e.g. `"news1"` or `"nf12"`,
+//!   and cross-references and antonyms in their XML form, e.g.
+//!   `"漢字・かんじ・2"`.
+//! - Enum variants without data are strings and variants with data are
+//!   objects with a single key. Kanjidic enums use the values of the
+//!   `r_type`, `dr_type` and `rad_type` attributes, e.g. `"pinyin"`,
+//!   `{"ja_on": [true, "kan"]}` (jouyou approval, on'yomi type),
+//!   `{"nelson_c": "123"}` or `{"moro": ["1234", 5, 200]}` (index, volume,
+//!   page). Grades are `{"kyouiku": 1}`, `"jouyou"`, `"jinmeiyou"` or
+//!   `"jouyou_variant"`.
+//!
+//! For example, a JMdict entry:
+//!
+//! ```json
+//! {"seq":1358280,
+//!  "reading":[{"text":"たべる","no_kanji":false,"pri_ref":["ichi1"],"restrict":[],"info":[]}],
+//!  "kanji":[{"text":"食べる","info":[],"pri_ref":["ichi1"]}],
+//!  "sense":[{"restrict_reading":[],"restrict_kanji":[],"cross_refs":[],
+//!            "gloss":[{"content":"to eat","lang":"eng","gender":null,"type":null}],
+//!            "antonyms":[],"pos":["v1","vt"],"fields":[],"misc":[],
+//!            "source_lang":[],"dialects":[],"info":[]}]}
+//! ```
+
+use crate::jmdict::JMDict;
+use crate::jmnedict::JMnedict;
+use crate::kanjidic::Kanjidic;
+use crate::tatoeba::Tatoeba;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::io::{self, BufRead, Write};
+
+/// Writes each item as JSON on its own line.
+pub fn write_lines<'a, W, T, I>(mut writer: W, items: I) -> io::Result<()>
+where
+    W: Write,
+    T: Serialize + 'a,
+    I: IntoIterator<Item = &'a T>,
+{
+    for item in items {
+        serde_json::to_writer(&mut writer, item)?;
+        writer.write_all(b"\n")?;
+    }
+    writer.flush()
+}
+
+/// Reads items written by [`write_lines`], skipping blank lines.
+pub fn read_lines<R, T>(reader: R) -> impl Iterator<Item = io::Result<T>>
+where
+    R: BufRead,
+    T: DeserializeOwned,
+{
+    reader
+        .lines()
+        .filter(|line| line.as_ref().map_or(true, |l| !l.trim().is_empty()))
+        .map(|line| Ok(serde_json::from_str(&line?)?))
+}
+
+impl JMDict {
+    pub fn write_json_lines<W: Write>(&self, writer: W) -> io::Result<()> {
+        write_lines(writer, &self.entries)
+    }
+}
+
+impl JMnedict {
+    pub fn write_json_lines<W: Write>(&self, writer: W) -> io::Result<()> {
+        write_lines(writer, &self.entries)
+    }
+}
+
+impl Kanjidic {
+    /// Writes the entries only; the header versions are not included.
+    pub fn write_json_lines<W: Write>(&self, writer: W) -> io::Result<()> {
+        write_lines(writer, &self.entries)
+    }
+}
+
+impl Tatoeba {
+    pub fn write_json_lines<W: Write>(&self, writer: W) -> io::Result<()> {
+        write_lines(writer, &self.entries)
+    }
+}
@@ -0,0 +1,160 @@
+//! Kana classification and normalization, used to compare readings
+//! regardless of the script they are written in.
+
+/// Controls which differences [`normalize`] folds away.
+#[derive(Debug, Clone)]
+pub struct NormalizeOptions {
+    // Convert katakana to hiragana.
+    pub fold_katakana: bool,
+    // Convert half-width katakana to full-width, composing voiced marks.
+    pub full_width: bool,
+    // Replace the long vowel mark with the vowel it lengthens, so that
+    // "コーヒー" and "こおひい" compare equal.
+    pub expand_long_vowels: bool,
+}
+
+impl Default for NormalizeOptions {
+    fn default() -> Self {
+        NormalizeOptions {
+            fold_katakana: true,
+            full_width: true,
+            expand_long_vowels: false,
+        }
+    }
+}
+
+pub fn normalize(text: &str, options: &NormalizeOptions) -> String {
+    let mut text = if options.full_width {
+        to_full_width(text)
+    } else {
+        text.to_owned()
+    };
+    if options.fold_katakana {
+        text = to_hiragana(&text);
+    }
+    if options.expand_long_vowels {
+        text = expand_long_vowels(&text);
+    }
+    text
+}
+
+pub fn is_hiragana(c: char) -> bool {
+    matches!(c, '\u{3041}'..='\u{309F}')
+}
+
+pub fn is_katakana(c: char) -> bool {
+    matches!(c, '\u{30A0}'..='\u{30FF}' | '\u{FF66}'..='\u{FF9F}')
+}
+
+pub fn is_kana(c: char) -> bool {
+    is_hiragana(c) || is_katakana(c)
+}
+
+pub fn is_all_kana(text: &str) -> bool {
+    text.chars().all(is_kana)
+}
+
+pub fn to_hiragana(text: &str) -> String {
+    text.chars().map(to_hiragana_char).collect()
+}
+
+pub(crate) fn to_hiragana_char(c: char) -> char {
+    match c {
+        // ァ..ヶ and the iteration marks ヽヾ have hiragana counterparts
+        // exactly 0x60 below.
+        '\u{30A1}'..='\u{30F6}' | '\u{30FD}' | '\u{30FE}' => shift(c, -0x60),
+        _ => c,
+    }
+}
+
+pub fn to_katakana(text: &str) -> String {
+    text.chars()
+        .map(|c| match c {
+            '\u{3041}'..='\u{3096}' | '\u{309D}' | '\u{309E}' => shift(c, 0x60),
+            _ => c,
+        })
+        .collect()
+}
+
+const HALF_WIDTH: &str = "。「」、・ヲァィゥェォャュョッーアイウエオカキクケコサシスセソタチツテトナニヌネノハヒフヘホマミムメモヤユヨラリルレロワン";
+
+/// Converts half-width katakana (U+FF61..U+FF9F) to full-width, combining
+/// a following voiced or semi-voiced sound mark into the preceding kana.
+pub fn to_full_width(text: &str) -> String {
+    let mut converted = String::with_capacity(text.len());
+
+    for c in text.chars() {
+        match c {
+            '\u{FF9E}' => match converted.pop() {
+                Some('ウ') => converted.push('ヴ'),
+                Some(prev) if has_voiced(prev) => converted.push(shift(prev, 1)),
+                Some(prev) => {
+                    converted.push(prev);
+                    converted.push('゛');
+                }
+                None => converted.push('゛'),
+            },
+            '\u{FF9F}' => match converted.pop() {
+                Some(prev) if has_semi_voiced(prev) => converted.push(shift(prev, 2)),
+                Some(prev) => {
+                    converted.push(prev);
+                    converted.push('゜');
+                }
+                None => converted.push('゜'),
+            },
+            '\u{FF61}'..='\u{FF9D}' => {
+                let i = c as usize - 0xFF61;
+                converted.extend(HALF_WIDTH.chars().nth(i));
+            }
+            _ => converted.push(c),
+        }
+    }
+
+    converted
+}
+
+/// Replaces each long vowel mark following a kana with the vowel of that
+/// kana, in the script of the preceding character.
+pub fn expand_long_vowels(text: &str) -> String {
+    let mut expanded = String::with_capacity(text.len());
+    let mut prev: Option<char> = None;
+
+    for c in text.chars() {
+        let replacement = match (c, prev) {
+            ('ー', Some(p)) => vowel(p).map(|v| if is_katakana(p) { shift(v, 0x60) } else { v }),
+            _ => None,
+        };
+        let c = replacement.unwrap_or(c);
+        expanded.push(c);
+        prev = Some(c);
+    }
+
+    expanded
+}
+
+fn vowel(c: char) -> Option<char> {
+    let hiragana = to_hiragana(&c.to_string()).chars().next()?;
+    let rows = [
+        ('あ', "あぁかがさざただなはばぱまやゃらわゎ"),
+        ('い', "いぃきぎしじちぢにひびぴみりゐ"),
+        ('う', "うぅくぐすずつづっぬふぶぷむゆゅるゔ"),
+        ('え', "えぇけげせぜてでねへべぺめれゑ"),
+        ('お', "おぉこごそぞとどのほぼぽもよょろを"),
+    ];
+
+    rows.iter()
+        .find(|(_, row)| row.contains(hiragana))
+        .map(|(v, _)| *v)
+}
+
+fn has_voiced(c: char) -> bool {
+    "カキクケコサシスセソタチツテトハヒフヘホ".contains(c)
+}
+
+fn has_semi_voiced(c: char) -> bool {
+    "ハヒフヘホ".contains(c)
+}
+
+fn shift(c: char, offset: i32) -> char {
+    std::char::from_u32((c as i32 + offset) as u32).unwrap_or(c)
+}
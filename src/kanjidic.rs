@@ -1,9 +1,18 @@
 use crate::errors::{ParseEnumError, ParseError, XmlError};
+use crate::kana::{self, NormalizeOptions};
+use crate::language::Language;
 use crate::radicals;
-use crate::util::{self, find_child_tag_err, get_node_attr, get_node_text};
+use crate::romaji;
+use crate::stream::ElementReader;
+use crate::util::{find_child_tag_err, get_node_attr, get_node_text};
 use roxmltree::{Document, Node};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{BufRead, BufReader};
 
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Kanjidic {
     pub file_version: u32,
     pub database_version: String,
@@ -13,6 +22,7 @@ pub struct Kanjidic {
 }
 
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Entry {
     pub literal: String,
     pub codepoints: Vec<Codepoint>,
@@ -31,61 +41,78 @@ pub struct Entry {
 }
 
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Codepoint {
     pub standard: String,
     pub value: String,
 }
 
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct ReadingMeaning {
     pub readings: Vec<Reading>,
     pub meanings: Vec<Meaning>,
 }
 
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Reading {
     pub value: String,
+    #[cfg_attr(feature = "serde", serde(rename = "type"))]
     pub typ: ReadingType,
 }
 
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
 pub enum ReadingType {
     Pinyin,
     KoreanR,
     KoreanH,
     Vietnam,
+    #[cfg_attr(feature = "serde", serde(rename = "ja_on"))]
     Onyomi(bool, OnyomiType),
+    #[cfg_attr(feature = "serde", serde(rename = "ja_kun"))]
     Kunyomi(bool),
 }
 
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
 pub enum OnyomiType {
     Kan,
     Go,
     Tou,
+    #[cfg_attr(feature = "serde", serde(rename = "kan'you"))]
     Kanyou,
     None,
 }
 
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Meaning {
     pub content: String,
-    pub language: String,
+    pub language: Language,
 }
 
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Radical {
     pub classification: RadicalType,
     pub value: String,
 }
 
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
 pub enum RadicalType {
     Classical,
     NelsonC,
 }
 
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
 pub enum Grade {
     Kyouiku(u32),
     Jouyou,
@@ -94,28 +121,40 @@ pub enum Grade {
 }
 
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
 pub enum DicRef {
     NelsonC(String),
     NelsonN(String),
+    #[cfg_attr(feature = "serde", serde(rename = "halpern_njecd"))]
     HalpernNJECD(String),
+    #[cfg_attr(feature = "serde", serde(rename = "halpern_kkd"))]
     HalpernKKD(String),
+    #[cfg_attr(feature = "serde", serde(rename = "halpern_kkld"))]
     HalpernKKLD(String),
+    #[cfg_attr(feature = "serde", serde(rename = "halpern_kkld_2ed"))]
     HalpernKKLD2(String),
     Heisig(String),
     Heisig6(String),
     Gakken(String),
     OneillNames(String),
+    #[cfg_attr(feature = "serde", serde(rename = "oneill_kk"))]
     OneillKK(String),
+    #[cfg_attr(feature = "serde", serde(rename = "neill_kk"))]
     NeillKK(String),
     Moro(String, Option<u32>, Option<u32>),
     Henshall(String),
+    #[cfg_attr(feature = "serde", serde(rename = "sh_kk"))]
     SHKK(String),
+    #[cfg_attr(feature = "serde", serde(rename = "sh_kk2"))]
     SHKK2(String),
     Sakade(String),
+    #[cfg_attr(feature = "serde", serde(rename = "jf_cards"))]
     JFCards(String),
     Henshall3(String),
     TuttCards(String),
     Crowley(String),
+    #[cfg_attr(feature = "serde", serde(rename = "kanji_in_context"))]
     InContext(String),
     BusyPeople(String),
     KodanshaCompact(String),
@@ -144,42 +183,146 @@ impl Kanjidic {
                 e.reading_meanings
                     .iter()
                     .flat_map(|rm| &rm.meanings)
-                    .any(|m| predicate(&m))
+                    .any(&predicate)
+            })
+            .collect()
+    }
+
+    /// Characters with at least one meaning in `language`.
+    pub fn filter_language(&self, language: Language) -> Vec<&Entry> {
+        self.entries
+            .iter()
+            .filter(|e| !e.meanings_in(language).is_empty())
+            .collect()
+    }
+
+    /// Finds characters with an on, kun or nanori reading matching `reading`
+    /// after kana normalization, so hiragana input also matches on'yomi.
+    /// Kun readings match either in full ("たべる" for "た.べる") or by the
+    /// part before the okurigana ("た").
+    pub fn search_normalized(&self, reading: &str, options: &NormalizeOptions) -> Vec<&Entry> {
+        let reading = kana::normalize(reading, options);
+        self.entries
+            .iter()
+            .filter(|e| {
+                let readings = e
+                    .reading_meanings
+                    .iter()
+                    .flat_map(|rm| &rm.readings)
+                    .filter(|r| r.is_japanese())
+                    .map(|r| &r.value)
+                    .chain(&e.nanori_readings);
+
+                readings.map(|r| r.trim_matches('-')).any(|r| {
+                    let stem = r.split('.').next().unwrap_or(r);
+                    kana::normalize(&r.replace('.', ""), options) == reading
+                        || kana::normalize(stem, options) == reading
+                })
             })
             .collect()
     }
 }
 
+impl Reading {
+    pub fn is_japanese(&self) -> bool {
+        matches!(self.typ, ReadingType::Onyomi(..) | ReadingType::Kunyomi(_))
+    }
+
+    /// The reading in Latin script, or `None` for readings that are not
+    /// Japanese. Okurigana and affix markers are kept, e.g. "ta.beru".
+    pub fn romanize(&self, options: &romaji::RomanizeOptions) -> Option<String> {
+        if self.is_japanese() {
+            Some(romaji::romanize(&self.value, options))
+        } else {
+            None
+        }
+    }
+}
+
+impl Entry {
+    pub fn meanings_in(&self, language: Language) -> Vec<&Meaning> {
+        self.reading_meanings
+            .iter()
+            .flat_map(|rm| &rm.meanings)
+            .filter(|m| m.language == language)
+            .collect()
+    }
+
+    pub fn romanized_nanori(&self, options: &romaji::RomanizeOptions) -> Vec<String> {
+        self.nanori_readings
+            .iter()
+            .map(|n| romaji::romanize(n, options))
+            .collect()
+    }
+}
+
 const_strs!(
-    ROOT: "kanjidic2",
     HEADER: "header",
     CHARACTER: "character"
 );
 
 impl Kanjidic {
     pub fn from_file(filepath: &str) -> Result<Self, ParseError> {
-        let contents = util::read_file(filepath)?;
-        let doc = Document::parse(&contents).map_err(XmlError::Roxml)?;
-        let root = find_child_tag_err(doc.root(), ROOT)?;
+        let file = File::open(filepath)?;
+        let mut stream = Kanjidic::stream(BufReader::new(file))?;
+        let entries = stream.by_ref().collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Kanjidic {
+            file_version: stream.file_version,
+            database_version: stream.database_version,
+            creation_date: stream.creation_date,
+            entries,
+        })
+    }
 
-        let header = find_child_tag_err(root, HEADER)?;
-        let (file_version, database_version, creation_date) = parse_header(header)?;
+    /// Parses the `<header>` of a Kanjidic2 XML document and returns an
+    /// iterator that lazily parses each `<character>` that follows it.
+    pub fn stream<R: BufRead>(reader: R) -> Result<EntryStream<R>, ParseError> {
+        let mut elements = ElementReader::new(reader);
 
-        let entries: Vec<_> = root
-            .children()
-            .filter(|c| c.is_element() && c.tag_name().name() == CHARACTER)
-            .map(|c| parse_entry(c))
-            .collect::<Result<Vec<_>, _>>()?;
+        let header_xml = elements
+            .next_element(HEADER)?
+            .ok_or_else(|| XmlError::MissingTag(HEADER.to_owned()))?;
+        let header_doc = Document::parse(&header_xml).map_err(XmlError::Roxml)?;
+        let (file_version, database_version, creation_date) =
+            parse_header(header_doc.root_element())?;
 
-        Ok(Kanjidic {
+        Ok(EntryStream {
             file_version,
             database_version,
             creation_date,
-            entries,
+            elements,
         })
     }
 }
 
+/// Iterator over the characters of a Kanjidic2 document, created by
+/// [`Kanjidic::stream`].
+pub struct EntryStream<R> {
+    pub file_version: u32,
+    pub database_version: String,
+    pub creation_date: String,
+
+    elements: ElementReader<R>,
+}
+
+impl<R: BufRead> Iterator for EntryStream<R> {
+    type Item = Result<Entry, ParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.elements.next_element(CHARACTER) {
+            Ok(Some(xml)) => Some(parse_entry_xml(&xml)),
+            Ok(None) => None,
+            Err(err) => Some(Err(err)),
+        }
+    }
+}
+
+fn parse_entry_xml(xml: &str) -> Result<Entry, ParseError> {
+    let doc = Document::parse(xml).map_err(XmlError::Roxml)?;
+    parse_entry(doc.root_element())
+}
+
 const_strs!(
     FILE_VERSION: "file_version",
     DATABASE_VERSION: "database_version",
@@ -270,9 +413,9 @@ fn parse_entry(n: Node) -> Result<Entry, ParseError> {
         stroke_miscounts: misc.stroke_miscounts,
         freq: misc.freq,
         old_jlpt: misc.old_jlpt,
-        dic_refs: dic_refs_op.unwrap_or(Vec::new()),
-        reading_meanings: readings_meanings_op.unwrap_or(Vec::new()),
-        nanori_readings: nanori_op.unwrap_or(Vec::new()),
+        dic_refs: dic_refs_op.unwrap_or_default(),
+        reading_meanings: readings_meanings_op.unwrap_or_default(),
+        nanori_readings: nanori_op.unwrap_or_default(),
     })
 }
 
@@ -462,6 +605,7 @@ const_strs!(
     READING_JA_STATUS: "r_status",
     MEANING: "meaning",
     MEANING_LANG: "m_lang",
+    MEANING_LANG_DEFAULT: "en",
 
     NANORI: "nanori"
 );
@@ -500,7 +644,8 @@ fn parse_reading_group(n: Node) -> Result<ReadingMeaning, ParseError> {
                 readings.push(reading);
             }
             MEANING => {
-                let language = c.attribute(MEANING_LANG).unwrap_or("en").to_owned();
+                let language = c.attribute(MEANING_LANG).unwrap_or(MEANING_LANG_DEFAULT);
+                let language = Language::from_iso639_1(language)?;
                 let content = get_node_text(c)?.into_owned();
                 meanings.push(Meaning { content, language });
             }
@@ -552,8 +697,5 @@ fn parse_reading(n: Node) -> Result<Reading, ParseError> {
 }
 
 fn get_jouyou_approved(n: Node) -> bool {
-    match get_node_attr(n, READING_JA_STATUS) {
-        Ok(_) => true,
-        Err(_) => false,
-    }
+    get_node_attr(n, READING_JA_STATUS).is_ok()
 }
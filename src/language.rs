@@ -0,0 +1,165 @@
+//! Languages as identified by the different ISO 639 code sets used by the
+//! source files: JMdict uses ISO 639-2 (bibliographic) codes, Kanjidic
+//! ISO 639-1 codes and Tatoeba ISO 639-3 codes.
+
+use crate::errors::ParseError;
+use std::fmt;
+use std::str;
+
+/// A language, stored as its ISO 639-3 code.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Language {
+    code: [u8; 3],
+}
+
+impl Language {
+    pub const DUTCH: Language = Language::new(b"nld");
+    pub const ENGLISH: Language = Language::new(b"eng");
+    pub const FRENCH: Language = Language::new(b"fra");
+    pub const GERMAN: Language = Language::new(b"deu");
+    pub const HUNGARIAN: Language = Language::new(b"hun");
+    pub const JAPANESE: Language = Language::new(b"jpn");
+    pub const PORTUGUESE: Language = Language::new(b"por");
+    pub const RUSSIAN: Language = Language::new(b"rus");
+    pub const SLOVENIAN: Language = Language::new(b"slv");
+    pub const SPANISH: Language = Language::new(b"spa");
+    pub const SWEDISH: Language = Language::new(b"swe");
+
+    pub(crate) const fn new(code: &[u8; 3]) -> Self {
+        Language { code: *code }
+    }
+
+    /// Parses a two-letter ISO 639-1 code, e.g. "en" or "es".
+    pub fn from_iso639_1(code: &str) -> Result<Self, ParseError> {
+        CODES
+            .iter()
+            .find(|(iso1, ..)| *iso1 == code)
+            .map(|(.., iso3)| Language::new(iso3))
+            .ok_or_else(|| ParseError::ParseLanguage6391(code.to_owned()))
+    }
+
+    /// Parses a three-letter ISO 639-2 code, either bibliographic ("ger")
+    /// or terminological ("deu").
+    pub fn from_iso639_2(code: &str) -> Result<Self, ParseError> {
+        let terminological = CODES
+            .iter()
+            .find(|(_, iso2b, _)| !iso2b.is_empty() && *iso2b == code)
+            .map(|(.., iso3)| Language::new(iso3));
+        match terminological {
+            Some(language) => Ok(language),
+            None => parse_three_letter(code)
+                .ok_or_else(|| ParseError::ParseLanguage6392(code.to_owned())),
+        }
+    }
+
+    /// Parses a three-letter ISO 639-3 code, e.g. "jpn" or "cmn".
+    pub fn from_iso639_3(code: &str) -> Result<Self, ParseError> {
+        parse_three_letter(code).ok_or_else(|| ParseError::ParseLanguage6393(code.to_owned()))
+    }
+
+    /// The ISO 639-1 code, if the language has one.
+    pub fn iso639_1(&self) -> Option<&'static str> {
+        self.row().map(|(iso1, ..)| *iso1)
+    }
+
+    /// The bibliographic ISO 639-2 code, as used by JMdict. For most
+    /// languages this is the same as the ISO 639-3 code.
+    pub fn iso639_2(&self) -> &str {
+        match self.row() {
+            Some((_, iso2b, _)) if !iso2b.is_empty() => iso2b,
+            _ => self.iso639_3(),
+        }
+    }
+
+    pub fn iso639_3(&self) -> &str {
+        str::from_utf8(&self.code).unwrap_or_default()
+    }
+
+    fn row(&self) -> Option<&'static (&'static str, &'static str, &'static [u8; 3])> {
+        CODES.iter().find(|(.., iso3)| **iso3 == self.code)
+    }
+}
+
+impl fmt::Display for Language {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.iso639_3())
+    }
+}
+
+/// Serialized as the ISO 639-3 code.
+#[cfg(feature = "serde")]
+impl serde::Serialize for Language {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.iso639_3())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Language {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let code = String::deserialize(deserializer)?;
+        Language::from_iso639_3(&code).map_err(serde::de::Error::custom)
+    }
+}
+
+fn parse_three_letter(code: &str) -> Option<Language> {
+    match code.as_bytes() {
+        &[a, b, c] if code.bytes().all(|b| b.is_ascii_lowercase()) => {
+            Some(Language::new(&[a, b, c]))
+        }
+        _ => None,
+    }
+}
+
+// Every ISO 639-1 code, with the bibliographic ISO 639-2 code where it
+// differs from the terminological one, and the ISO 639-3 code, which is
+// the same as the terminological ISO 639-2 code.
+#[rustfmt::skip]
+const CODES: &[(&str, &str, &[u8; 3])] = &[
+    ("aa", "", b"aar"), ("ab", "", b"abk"), ("ae", "", b"ave"), ("af", "", b"afr"),
+    ("ak", "", b"aka"), ("am", "", b"amh"), ("an", "", b"arg"), ("ar", "", b"ara"),
+    ("as", "", b"asm"), ("av", "", b"ava"), ("ay", "", b"aym"), ("az", "", b"aze"),
+    ("ba", "", b"bak"), ("be", "", b"bel"), ("bg", "", b"bul"), ("bi", "", b"bis"),
+    ("bm", "", b"bam"), ("bn", "", b"ben"), ("bo", "tib", b"bod"), ("br", "", b"bre"),
+    ("bs", "", b"bos"), ("ca", "", b"cat"), ("ce", "", b"che"), ("ch", "", b"cha"),
+    ("co", "", b"cos"), ("cr", "", b"cre"), ("cs", "cze", b"ces"), ("cu", "", b"chu"),
+    ("cv", "", b"chv"), ("cy", "wel", b"cym"), ("da", "", b"dan"), ("de", "ger", b"deu"),
+    ("dv", "", b"div"), ("dz", "", b"dzo"), ("ee", "", b"ewe"), ("el", "gre", b"ell"),
+    ("en", "", b"eng"), ("eo", "", b"epo"), ("es", "", b"spa"), ("et", "", b"est"),
+    ("eu", "baq", b"eus"), ("fa", "per", b"fas"), ("ff", "", b"ful"), ("fi", "", b"fin"),
+    ("fj", "", b"fij"), ("fo", "", b"fao"), ("fr", "fre", b"fra"), ("fy", "", b"fry"),
+    ("ga", "", b"gle"), ("gd", "", b"gla"), ("gl", "", b"glg"), ("gn", "", b"grn"),
+    ("gu", "", b"guj"), ("gv", "", b"glv"), ("ha", "", b"hau"), ("he", "", b"heb"),
+    ("hi", "", b"hin"), ("ho", "", b"hmo"), ("hr", "", b"hrv"), ("ht", "", b"hat"),
+    ("hu", "", b"hun"), ("hy", "arm", b"hye"), ("hz", "", b"her"), ("ia", "", b"ina"),
+    ("id", "", b"ind"), ("ie", "", b"ile"), ("ig", "", b"ibo"), ("ii", "", b"iii"),
+    ("ik", "", b"ipk"), ("io", "", b"ido"), ("is", "ice", b"isl"), ("it", "", b"ita"),
+    ("iu", "", b"iku"), ("ja", "", b"jpn"), ("jv", "", b"jav"), ("ka", "geo", b"kat"),
+    ("kg", "", b"kon"), ("ki", "", b"kik"), ("kj", "", b"kua"), ("kk", "", b"kaz"),
+    ("kl", "", b"kal"), ("km", "", b"khm"), ("kn", "", b"kan"), ("ko", "", b"kor"),
+    ("kr", "", b"kau"), ("ks", "", b"kas"), ("ku", "", b"kur"), ("kv", "", b"kom"),
+    ("kw", "", b"cor"), ("ky", "", b"kir"), ("la", "", b"lat"), ("lb", "", b"ltz"),
+    ("lg", "", b"lug"), ("li", "", b"lim"), ("ln", "", b"lin"), ("lo", "", b"lao"),
+    ("lt", "", b"lit"), ("lu", "", b"lub"), ("lv", "", b"lav"), ("mg", "", b"mlg"),
+    ("mh", "", b"mah"), ("mi", "mao", b"mri"), ("mk", "mac", b"mkd"), ("ml", "", b"mal"),
+    ("mn", "", b"mon"), ("mr", "", b"mar"), ("ms", "may", b"msa"), ("mt", "", b"mlt"),
+    ("my", "bur", b"mya"), ("na", "", b"nau"), ("nb", "", b"nob"), ("nd", "", b"nde"),
+    ("ne", "", b"nep"), ("ng", "", b"ndo"), ("nl", "dut", b"nld"), ("nn", "", b"nno"),
+    ("no", "", b"nor"), ("nr", "", b"nbl"), ("nv", "", b"nav"), ("ny", "", b"nya"),
+    ("oc", "", b"oci"), ("oj", "", b"oji"), ("om", "", b"orm"), ("or", "", b"ori"),
+    ("os", "", b"oss"), ("pa", "", b"pan"), ("pi", "", b"pli"), ("pl", "", b"pol"),
+    ("ps", "", b"pus"), ("pt", "", b"por"), ("qu", "", b"que"), ("rm", "", b"roh"),
+    ("rn", "", b"run"), ("ro", "rum", b"ron"), ("ru", "", b"rus"), ("rw", "", b"kin"),
+    ("sa", "", b"san"), ("sc", "", b"srd"), ("sd", "", b"snd"), ("se", "", b"sme"),
+    ("sg", "", b"sag"), ("si", "", b"sin"), ("sk", "slo", b"slk"), ("sl", "", b"slv"),
+    ("sm", "", b"smo"), ("sn", "", b"sna"), ("so", "", b"som"), ("sq", "alb", b"sqi"),
+    ("sr", "", b"srp"), ("ss", "", b"ssw"), ("st", "", b"sot"), ("su", "", b"sun"),
+    ("sv", "", b"swe"), ("sw", "", b"swa"), ("ta", "", b"tam"), ("te", "", b"tel"),
+    ("tg", "", b"tgk"), ("th", "", b"tha"), ("ti", "", b"tir"), ("tk", "", b"tuk"),
+    ("tl", "", b"tgl"), ("tn", "", b"tsn"), ("to", "", b"ton"), ("tr", "", b"tur"),
+    ("ts", "", b"tso"), ("tt", "", b"tat"), ("tw", "", b"twi"), ("ty", "", b"tah"),
+    ("ug", "", b"uig"), ("uk", "", b"ukr"), ("ur", "", b"urd"), ("uz", "", b"uzb"),
+    ("ve", "", b"ven"), ("vi", "", b"vie"), ("vo", "", b"vol"), ("wa", "", b"wln"),
+    ("wo", "", b"wol"), ("xh", "", b"xho"), ("yi", "", b"yid"), ("yo", "", b"yor"),
+    ("za", "", b"zha"), ("zh", "chi", b"zho"), ("zu", "", b"zul"),
+];
@@ -1,11 +1,31 @@
 #[macro_use]
 mod util;
 
+pub mod cache;
+pub mod conjugate;
+pub mod deinflect;
+pub mod entities;
 pub mod errors;
+pub mod furigana;
+pub mod fuzzy;
+mod gloss;
+pub mod index;
 pub mod jmdict;
+pub mod jmnedict;
+#[cfg(feature = "serde")]
+pub mod json;
+pub mod kana;
 pub mod kanjidic;
+pub mod language;
+pub mod mapped;
+mod pattern;
 pub mod radicals;
+pub mod romaji;
+mod search;
+mod stream;
 pub mod tatoeba;
+pub mod tokenize;
+mod trie;
 
 #[cfg(test)]
 mod tests;
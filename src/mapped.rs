@@ -0,0 +1,911 @@
+//! A read-only JMdict format for memory mapping, whose entries are views
+//! that borrow their text from the mapped file.
+//!
+//! The file is made of fixed-size little-endian records. An entry refers to
+//! its kanji, readings and senses as (offset, count) ranges of records, and
+//! to text as (offset, length) ranges of UTF-8. Nothing is decoded when the
+//! file is opened, so startup costs the same whatever its size, and
+//! processes mapping the same file share its pages. Reads are bounds
+//! checked: a reference out of range reads as empty rather than panicking.
+//!
+//! The 40-byte header holds the magic bytes `JMDM`, the format version, the
+//! [`Source`] the dictionary was parsed from and the range of entries. It is
+//! checked the same way as the header of a [binary cache](crate::cache).
+//!
+//! The views offer the query methods of the parsed types and share their
+//! search code, except for conjugation tables, which are only built for
+//! parsed entries.
+
+use crate::cache::{self, Source};
+use crate::deinflect::Deinflection;
+use crate::entities::{Dialect, Field, KanjiInfo, Misc, PartOfSpeech, ReadingInfo};
+use crate::errors::CacheError;
+use crate::jmdict::{self, JMDict, PriRef, XRef};
+use crate::kana::NormalizeOptions;
+use crate::language::Language;
+use crate::romaji;
+use crate::search;
+use memmap2::Mmap;
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::fmt;
+use std::fs::File;
+use std::io;
+use std::path::Path;
+use std::str;
+
+const MAGIC: &[u8; 4] = b"JMDM";
+
+/// Bumped whenever the layout of any record changes.
+pub const FORMAT_VERSION: u32 = 2;
+
+const HEADER_SIZE: usize = 40;
+// Offset in an optional text reference marking it absent.
+const NONE: u32 = u32::MAX;
+
+// Record sizes in bytes.
+const TEXT: usize = 8;
+const LIST: usize = 8;
+const U32: usize = 4;
+const ENTRY: usize = U32 + 3 * LIST;
+const KANJI: usize = TEXT + 2 * LIST;
+const READING: usize = TEXT + U32 + 3 * LIST;
+const SENSE: usize = 11 * LIST;
+const GLOSS: usize = 3 * TEXT + 4;
+const LSOURCE: usize = TEXT + 4;
+
+/// A JMdict file mapped into memory.
+pub struct MappedJMDict {
+    data: Data,
+}
+
+enum Data {
+    Map(Mmap),
+    Bytes(Vec<u8>),
+}
+
+impl MappedJMDict {
+    /// Maps a file written by [`JMDict::save_mapped`] from the same
+    /// `source`.
+    ///
+    /// The file must not be modified in place while it is mapped.
+    /// [`JMDict::save_mapped`] replaces files by renaming a new file over
+    /// them, which leaves existing mappings untouched.
+    pub fn open<P: AsRef<Path>>(path: P, source: &Source) -> Result<Self, CacheError> {
+        let file = File::open(path)?;
+        // Safety: the mapping is only read, and files are only ever replaced
+        // as a whole, as documented above.
+        let map = unsafe { Mmap::map(&file)? };
+        MappedJMDict::new(Data::Map(map), source)
+    }
+
+    /// Reads the format from memory rather than from a mapped file.
+    pub fn from_bytes(bytes: Vec<u8>, source: &Source) -> Result<Self, CacheError> {
+        MappedJMDict::new(Data::Bytes(bytes), source)
+    }
+
+    fn new(data: Data, source: &Source) -> Result<Self, CacheError> {
+        let bytes = match &data {
+            Data::Map(map) => &map[..],
+            Data::Bytes(bytes) => &bytes[..],
+        };
+        if bytes.len() < HEADER_SIZE || !bytes.starts_with(MAGIC) {
+            return Err(CacheError::InvalidHeader);
+        }
+
+        let version = u32_at(bytes, 4);
+        if version != FORMAT_VERSION {
+            return Err(CacheError::FormatVersion(version));
+        }
+
+        let mapped_source = Source {
+            len: u64_at(bytes, 8),
+            modified: u64_at(bytes, 16),
+            version: optional_text_at(bytes, 24).map(str::to_owned),
+        };
+        if mapped_source != *source {
+            return Err(CacheError::SourceChanged);
+        }
+
+        let offset = u32_at(bytes, 32) as usize;
+        let count = u32_at(bytes, 36) as usize;
+        if count.saturating_mul(ENTRY).saturating_add(offset) > bytes.len() {
+            return Err(CacheError::Corrupt);
+        }
+
+        Ok(MappedJMDict { data })
+    }
+
+    fn bytes(&self) -> &[u8] {
+        match &self.data {
+            Data::Map(map) => map,
+            Data::Bytes(bytes) => bytes,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn entries(&self) -> impl ExactSizeIterator<Item = Entry<'_>> + Clone {
+        List::at(self.bytes(), 32, ENTRY, |data, at| Entry { data, at })
+    }
+
+    pub fn find_seq(&self, seq: u32) -> Option<Entry<'_>> {
+        self.entries().find(|e| e.seq() == seq)
+    }
+
+    pub fn filter<F>(&self, predicate: F) -> Vec<Entry<'_>>
+    where
+        F: Fn(&Entry) -> bool,
+    {
+        self.entries().filter(|e| predicate(e)).collect()
+    }
+
+    pub fn filter_reading<F>(&self, predicate: F) -> Vec<Entry<'_>>
+    where
+        F: Fn(&Reading) -> bool,
+    {
+        self.entries()
+            .filter(|e| e.reading().any(|r| predicate(&r)))
+            .collect()
+    }
+
+    pub fn filter_kanji<F>(&self, predicate: F) -> Vec<Entry<'_>>
+    where
+        F: Fn(&Kanji) -> bool,
+    {
+        self.entries()
+            .filter(|e| e.kanji().any(|k| predicate(&k)))
+            .collect()
+    }
+
+    pub fn filter_gloss<F>(&self, predicate: F) -> Vec<Entry<'_>>
+    where
+        F: Fn(&Gloss) -> bool,
+    {
+        self.entries()
+            .filter(|e| e.sense().flat_map(|s| s.gloss()).any(|g| predicate(&g)))
+            .collect()
+    }
+
+    /// Entries with at least one sense glossed in `language`.
+    pub fn filter_language(&self, language: Language) -> Vec<Entry<'_>> {
+        self.entries()
+            .filter(|e| !e.senses_in(language).is_empty())
+            .collect()
+    }
+
+    pub fn search(&self, phrase: &str) -> Vec<Entry<'_>> {
+        self.entries()
+            .filter(|e| {
+                e.reading().any(|r| r.text() == phrase) || e.kanji().any(|k| k.text() == phrase)
+            })
+            .collect()
+    }
+
+    /// Like [`MappedJMDict::search`], but compares kana after normalizing
+    /// both the phrase and the dictionary text.
+    pub fn search_normalized(&self, phrase: &str, options: &NormalizeOptions) -> Vec<Entry<'_>> {
+        search::normalized(self.entries(), phrase, options)
+    }
+
+    /// Finds entries with a reading matching romaji input, as
+    /// [`JMDict::search_romaji`] does.
+    pub fn search_romaji(&self, romaji: &str) -> Vec<Entry<'_>> {
+        search::romaji(self.entries(), romaji)
+    }
+
+    /// Finds the dictionary forms of a possibly inflected word, as
+    /// [`JMDict::search_deinflected`] does.
+    pub fn search_deinflected(&self, word: &str) -> Vec<(Entry<'_>, Deinflection)> {
+        search::deinflected(self.entries(), word)
+    }
+
+    pub fn antonyms(&self, entry: &Entry) -> Vec<(Entry<'_>, Sense<'_>)> {
+        let ant = entry.sense().flat_map(|s| s.antonyms());
+        ant.flat_map(|a| self.resolve_xref(&a)).collect()
+    }
+
+    pub fn cross_refs(&self, entry: &Entry) -> Vec<(Entry<'_>, Sense<'_>)> {
+        let xrefs = entry.sense().flat_map(|s| s.cross_refs());
+        xrefs.flat_map(|x| self.resolve_xref(&x)).collect()
+    }
+
+    /// Returns the senses targeted by a cross-reference; every sense of the
+    /// matching entries if the reference does not name one.
+    pub fn resolve_xref(&self, xref: &XRef) -> Vec<(Entry<'_>, Sense<'_>)> {
+        self.entries()
+            .flat_map(|e| e.xref_senses(xref).into_iter().map(move |s| (e, s)))
+            .collect()
+    }
+}
+
+#[derive(Clone, Copy)]
+pub struct Entry<'a> {
+    data: &'a [u8],
+    at: usize,
+}
+
+#[derive(Clone, Copy)]
+pub struct Kanji<'a> {
+    data: &'a [u8],
+    at: usize,
+}
+
+#[derive(Clone, Copy)]
+pub struct Reading<'a> {
+    data: &'a [u8],
+    at: usize,
+}
+
+#[derive(Clone, Copy)]
+pub struct Sense<'a> {
+    data: &'a [u8],
+    at: usize,
+}
+
+#[derive(Clone, Copy)]
+pub struct Gloss<'a> {
+    data: &'a [u8],
+    at: usize,
+}
+
+#[derive(Clone, Copy)]
+pub struct LSource<'a> {
+    data: &'a [u8],
+    at: usize,
+}
+
+/// A written form of an entry, as [`jmdict::Form`].
+#[derive(Debug, Clone)]
+pub struct Form<'a> {
+    pub kanji: Option<Kanji<'a>>,
+    pub reading: Reading<'a>,
+    pub senses: Vec<Sense<'a>>,
+}
+
+impl<'a> Entry<'a> {
+    pub fn seq(&self) -> u32 {
+        u32_at(self.data, self.at)
+    }
+
+    pub fn kanji(&self) -> impl ExactSizeIterator<Item = Kanji<'a>> + Clone + 'a {
+        List::at(self.data, self.at + 4, KANJI, |data, at| Kanji { data, at })
+    }
+
+    pub fn reading(&self) -> impl ExactSizeIterator<Item = Reading<'a>> + Clone + 'a {
+        List::at(self.data, self.at + 12, READING, |data, at| Reading {
+            data,
+            at,
+        })
+    }
+
+    pub fn sense(&self) -> impl ExactSizeIterator<Item = Sense<'a>> + Clone + 'a {
+        List::at(self.data, self.at + 20, SENSE, |data, at| Sense {
+            data,
+            at,
+        })
+    }
+
+    /// Whether any kanji or reading element of the entry is marked common.
+    pub fn is_common(&self) -> bool {
+        self.kanji().any(|k| k.is_common()) || self.reading().any(|r| r.is_common())
+    }
+
+    /// The best `nfXX` frequency band among the kanji and reading elements.
+    pub fn frequency_rank(&self) -> Option<u32> {
+        let kanji = self.kanji().filter_map(|k| k.frequency_rank());
+        let reading = self.reading().filter_map(|r| r.frequency_rank());
+        kanji.chain(reading).min()
+    }
+
+    /// Every combination of kanji and reading the entry may be written
+    /// with, as [`jmdict::Entry::forms`].
+    pub fn forms(&self) -> Vec<Form<'a>> {
+        let mut forms = Vec::new();
+
+        for k in self.kanji() {
+            for r in self.reading().filter(|r| r.applies_to(&k)) {
+                forms.push(self.form(Some(k), r));
+            }
+        }
+
+        for r in self.reading() {
+            if r.no_kanji() || self.kanji().len() == 0 {
+                forms.push(self.form(None, r));
+            }
+        }
+
+        forms
+    }
+
+    fn form(&self, kanji: Option<Kanji<'a>>, reading: Reading<'a>) -> Form<'a> {
+        let senses = self
+            .sense()
+            .filter(|s| s.applies_to(kanji.as_ref(), &reading))
+            .collect();
+
+        Form {
+            kanji,
+            reading,
+            senses,
+        }
+    }
+
+    /// Senses with a gloss in `language`.
+    pub fn senses_in(&self, language: Language) -> Vec<Sense<'a>> {
+        self.sense()
+            .filter(|s| s.gloss().any(|g| g.lang() == language))
+            .collect()
+    }
+
+    fn xref_senses(&self, xref: &XRef) -> Vec<Sense<'a>> {
+        let kanji_matches = xref
+            .kanji
+            .as_deref()
+            .is_none_or(|kanji| self.kanji().any(|k| k.text() == kanji));
+        let reading_matches = xref
+            .reading
+            .as_deref()
+            .is_none_or(|reading| self.reading().any(|r| r.text() == reading));
+        if !kanji_matches || !reading_matches {
+            return Vec::new();
+        }
+
+        match xref.sense_index {
+            Some(i) => self.sense().nth(i).into_iter().collect(),
+            None => self.sense().collect(),
+        }
+    }
+}
+
+impl<'a> Kanji<'a> {
+    pub fn text(&self) -> &'a str {
+        text_at(self.data, self.at)
+    }
+
+    pub fn info(&self) -> impl ExactSizeIterator<Item = KanjiInfo> + Clone + 'a {
+        List::at(self.data, self.at + 8, TEXT, |data, at| {
+            KanjiInfo::from_code(text_at(data, at))
+        })
+    }
+
+    pub fn pri_ref(&self) -> impl ExactSizeIterator<Item = PriRef> + Clone + 'a {
+        List::at(self.data, self.at + 16, U32, |data, at| {
+            decode_pri_ref(u32_at(data, at))
+        })
+    }
+
+    pub fn is_search_only(&self) -> bool {
+        self.info().any(|i| i == KanjiInfo::SearchOnlyKanji)
+    }
+
+    pub fn is_irregular(&self) -> bool {
+        self.info().any(|i| jmdict::is_irregular_kanji(&i))
+    }
+
+    pub fn is_common(&self) -> bool {
+        self.pri_ref().any(|p| p.is_common())
+    }
+
+    pub fn frequency_rank(&self) -> Option<u32> {
+        frequency_rank(self.pri_ref())
+    }
+}
+
+impl<'a> Reading<'a> {
+    pub fn text(&self) -> &'a str {
+        text_at(self.data, self.at)
+    }
+
+    pub fn no_kanji(&self) -> bool {
+        u32_at(self.data, self.at + 8) != 0
+    }
+
+    pub fn pri_ref(&self) -> impl ExactSizeIterator<Item = PriRef> + Clone + 'a {
+        List::at(self.data, self.at + 12, U32, |data, at| {
+            decode_pri_ref(u32_at(data, at))
+        })
+    }
+
+    pub fn restrict(&self) -> impl ExactSizeIterator<Item = &'a str> + Clone + 'a {
+        List::at(self.data, self.at + 20, TEXT, text_at)
+    }
+
+    pub fn info(&self) -> impl ExactSizeIterator<Item = ReadingInfo> + Clone + 'a {
+        List::at(self.data, self.at + 28, TEXT, |data, at| {
+            ReadingInfo::from_code(text_at(data, at))
+        })
+    }
+
+    /// Whether the reading may be used with the given kanji element.
+    pub fn applies_to(&self, kanji: &Kanji) -> bool {
+        let mut restrict = self.restrict();
+        !self.no_kanji() && (restrict.len() == 0 || restrict.any(|r| r == kanji.text()))
+    }
+
+    pub fn is_search_only(&self) -> bool {
+        self.info().any(|i| i == ReadingInfo::SearchOnlyKana)
+    }
+
+    pub fn is_irregular(&self) -> bool {
+        self.info().any(|i| i == ReadingInfo::IrregularKana)
+    }
+
+    pub fn is_common(&self) -> bool {
+        self.pri_ref().any(|p| p.is_common())
+    }
+
+    pub fn frequency_rank(&self) -> Option<u32> {
+        frequency_rank(self.pri_ref())
+    }
+
+    pub fn romanize(&self, options: &romaji::RomanizeOptions) -> String {
+        romaji::romanize(self.text(), options)
+    }
+}
+
+impl<'a> Sense<'a> {
+    fn texts(&self, field: usize) -> List<'a, &'a str> {
+        List::at(self.data, self.at + field * LIST, TEXT, text_at)
+    }
+
+    pub fn restrict_reading(&self) -> impl ExactSizeIterator<Item = &'a str> + Clone + 'a {
+        self.texts(0)
+    }
+
+    pub fn restrict_kanji(&self) -> impl ExactSizeIterator<Item = &'a str> + Clone + 'a {
+        self.texts(1)
+    }
+
+    pub fn cross_refs(&self) -> impl ExactSizeIterator<Item = XRef> + Clone + 'a {
+        self.texts(2).map(XRef::from)
+    }
+
+    pub fn gloss(&self) -> impl ExactSizeIterator<Item = Gloss<'a>> + Clone + 'a {
+        List::at(self.data, self.at + 3 * LIST, GLOSS, |data, at| Gloss {
+            data,
+            at,
+        })
+    }
+
+    pub fn antonyms(&self) -> impl ExactSizeIterator<Item = XRef> + Clone + 'a {
+        self.texts(4).map(XRef::from)
+    }
+
+    pub fn pos(&self) -> impl ExactSizeIterator<Item = PartOfSpeech> + Clone + 'a {
+        self.texts(5).map(PartOfSpeech::from_code)
+    }
+
+    pub fn fields(&self) -> impl ExactSizeIterator<Item = Field> + Clone + 'a {
+        self.texts(6).map(Field::from_code)
+    }
+
+    pub fn misc(&self) -> impl ExactSizeIterator<Item = Misc> + Clone + 'a {
+        self.texts(7).map(Misc::from_code)
+    }
+
+    pub fn source_lang(&self) -> impl ExactSizeIterator<Item = LSource<'a>> + Clone + 'a {
+        List::at(self.data, self.at + 8 * LIST, LSOURCE, |data, at| LSource {
+            data,
+            at,
+        })
+    }
+
+    pub fn dialects(&self) -> impl ExactSizeIterator<Item = Dialect> + Clone + 'a {
+        self.texts(9).map(Dialect::from_code)
+    }
+
+    pub fn info(&self) -> impl ExactSizeIterator<Item = &'a str> + Clone + 'a {
+        self.texts(10)
+    }
+
+    pub fn glosses_in(&self, language: Language) -> Vec<Gloss<'a>> {
+        self.gloss().filter(|g| g.lang() == language).collect()
+    }
+
+    /// Whether the sense applies to the form written with the given kanji
+    /// element, or with the reading alone if `kanji` is `None`.
+    pub fn applies_to(&self, kanji: Option<&Kanji>, reading: &Reading) -> bool {
+        let mut restrict_kanji = self.restrict_kanji();
+        let kanji_allowed = restrict_kanji.len() == 0
+            || kanji.is_some_and(|k| restrict_kanji.any(|r| r == k.text()));
+        let mut restrict_reading = self.restrict_reading();
+        let reading_allowed =
+            restrict_reading.len() == 0 || restrict_reading.any(|r| r == reading.text());
+
+        kanji_allowed && reading_allowed
+    }
+}
+
+impl<'a> Gloss<'a> {
+    pub fn content(&self) -> Option<&'a str> {
+        optional_text_at(self.data, self.at)
+    }
+
+    pub fn lang(&self) -> Language {
+        language_at(self.data, self.at + 8)
+    }
+
+    pub fn gender(&self) -> Option<&'a str> {
+        optional_text_at(self.data, self.at + 12)
+    }
+
+    pub fn typ(&self) -> Option<&'a str> {
+        optional_text_at(self.data, self.at + 20)
+    }
+}
+
+impl<'a> LSource<'a> {
+    pub fn content(&self) -> Option<&'a str> {
+        optional_text_at(self.data, self.at)
+    }
+
+    pub fn lang(&self) -> Language {
+        language_at(self.data, self.at + 8)
+    }
+
+    pub fn full(&self) -> bool {
+        self.flags() & 1 != 0
+    }
+
+    pub fn wasei(&self) -> bool {
+        self.flags() & 2 != 0
+    }
+
+    fn flags(&self) -> u8 {
+        self.data.get(self.at + 11).copied().unwrap_or(0)
+    }
+}
+
+impl fmt::Debug for Entry<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Entry")
+            .field("seq", &self.seq())
+            .field("reading", &self.reading().collect::<Vec<_>>())
+            .field("kanji", &self.kanji().collect::<Vec<_>>())
+            .field("sense", &self.sense().collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+impl fmt::Debug for Kanji<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Kanji")
+            .field("text", &self.text())
+            .field("info", &self.info().collect::<Vec<_>>())
+            .field("pri_ref", &self.pri_ref().collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+impl fmt::Debug for Reading<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Reading")
+            .field("text", &self.text())
+            .field("no_kanji", &self.no_kanji())
+            .field("pri_ref", &self.pri_ref().collect::<Vec<_>>())
+            .field("restrict", &self.restrict().collect::<Vec<_>>())
+            .field("info", &self.info().collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+impl fmt::Debug for Sense<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Sense")
+            .field(
+                "restrict_reading",
+                &self.restrict_reading().collect::<Vec<_>>(),
+            )
+            .field("restrict_kanji", &self.restrict_kanji().collect::<Vec<_>>())
+            .field("cross_refs", &self.cross_refs().collect::<Vec<_>>())
+            .field("gloss", &self.gloss().collect::<Vec<_>>())
+            .field("antonyms", &self.antonyms().collect::<Vec<_>>())
+            .field("pos", &self.pos().collect::<Vec<_>>())
+            .field("fields", &self.fields().collect::<Vec<_>>())
+            .field("misc", &self.misc().collect::<Vec<_>>())
+            .field("source_lang", &self.source_lang().collect::<Vec<_>>())
+            .field("dialects", &self.dialects().collect::<Vec<_>>())
+            .field("info", &self.info().collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+impl fmt::Debug for Gloss<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Gloss")
+            .field("content", &self.content())
+            .field("lang", &self.lang())
+            .field("gender", &self.gender())
+            .field("typ", &self.typ())
+            .finish()
+    }
+}
+
+impl fmt::Debug for LSource<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("LSource")
+            .field("content", &self.content())
+            .field("lang", &self.lang())
+            .field("full", &self.full())
+            .field("wasei", &self.wasei())
+            .finish()
+    }
+}
+
+// Iterator over a range of fixed-size records, read with `make`.
+#[derive(Clone)]
+struct List<'a, T> {
+    data: &'a [u8],
+    next: usize,
+    remaining: usize,
+    size: usize,
+    make: fn(&'a [u8], usize) -> T,
+}
+
+impl<'a, T> List<'a, T> {
+    // Reads the list reference at `at`.
+    fn at(data: &'a [u8], at: usize, size: usize, make: fn(&'a [u8], usize) -> T) -> Self {
+        let offset = u32_at(data, at) as usize;
+        let count = u32_at(data, at + 4) as usize;
+        // A corrupt count is clamped to the records that fit in the file.
+        let fits = data.len().saturating_sub(offset) / size;
+
+        List {
+            data,
+            next: offset,
+            remaining: count.min(fits),
+            size,
+            make,
+        }
+    }
+}
+
+impl<T> Iterator for List<'_, T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        if self.remaining == 0 {
+            return None;
+        }
+
+        let item = (self.make)(self.data, self.next);
+        self.next += self.size;
+        self.remaining -= 1;
+        Some(item)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<T> ExactSizeIterator for List<'_, T> {}
+
+fn u32_at(data: &[u8], at: usize) -> u32 {
+    match data.get(at..at + 4) {
+        Some(b) => u32::from_le_bytes([b[0], b[1], b[2], b[3]]),
+        None => 0,
+    }
+}
+
+fn u64_at(data: &[u8], at: usize) -> u64 {
+    u64::from(u32_at(data, at)) | u64::from(u32_at(data, at + 4)) << 32
+}
+
+fn text_at(data: &[u8], at: usize) -> &str {
+    let offset = u32_at(data, at) as usize;
+    let len = u32_at(data, at + 4) as usize;
+    data.get(offset..offset.saturating_add(len))
+        .and_then(|b| str::from_utf8(b).ok())
+        .unwrap_or("")
+}
+
+fn optional_text_at(data: &[u8], at: usize) -> Option<&str> {
+    match u32_at(data, at) {
+        NONE => None,
+        _ => Some(text_at(data, at)),
+    }
+}
+
+fn language_at(data: &[u8], at: usize) -> Language {
+    match data.get(at..at + 3) {
+        Some(b) => Language::new(&[b[0], b[1], b[2]]),
+        None => Language::new(b"und"),
+    }
+}
+
+fn frequency_rank(pri_refs: impl Iterator<Item = PriRef>) -> Option<u32> {
+    pri_refs
+        .filter_map(|p| match p {
+            PriRef::NF(n) => Some(n),
+            _ => None,
+        })
+        .min()
+}
+
+#[rustfmt::skip]
+const PRI_REFS: [PriRef; 8] = [
+    PriRef::News1, PriRef::News2, PriRef::Ichi1, PriRef::Ichi2,
+    PriRef::Spec1, PriRef::Spec2, PriRef::Gai1, PriRef::Gai2,
+];
+
+// The tags are numbered in the order of `PRI_REFS`, followed by the
+// `nfXX` bands.
+fn encode_pri_ref(pri_ref: &PriRef) -> u32 {
+    match pri_ref {
+        PriRef::NF(n) => n.saturating_add(PRI_REFS.len() as u32),
+        tag => PRI_REFS.iter().position(|p| p == tag).unwrap_or(0) as u32,
+    }
+}
+
+fn decode_pri_ref(code: u32) -> PriRef {
+    match PRI_REFS.get(code as usize) {
+        Some(tag) => *tag,
+        None => PriRef::NF(code - PRI_REFS.len() as u32),
+    }
+}
+
+impl JMDict {
+    /// Writes the dictionary in the format read by [`MappedJMDict`],
+    /// recording the XML file it was parsed from.
+    pub fn save_mapped<P: AsRef<Path>>(&self, path: P, source: &Source) -> Result<(), CacheError> {
+        let mut writer = Writer {
+            out: vec![0; HEADER_SIZE],
+            texts: HashMap::new(),
+        };
+        let mut entries = Vec::new();
+        writer.list(&self.entries, &mut entries, write_entry)?;
+        writer.offset()?;
+
+        let mut version = Vec::new();
+        writer.optional_text(source.version.as_deref(), &mut version)?;
+
+        let mut header = MAGIC.to_vec();
+        header.extend_from_slice(&FORMAT_VERSION.to_le_bytes());
+        header.extend_from_slice(&source.len.to_le_bytes());
+        header.extend_from_slice(&source.modified.to_le_bytes());
+        header.extend_from_slice(&version);
+        header.extend_from_slice(&entries);
+        writer.out[..HEADER_SIZE].copy_from_slice(&header);
+
+        cache::write_replacing(path.as_ref(), &writer.out)
+    }
+}
+
+struct Writer {
+    out: Vec<u8>,
+    // Offsets of the texts written so far, so that repeated texts such as
+    // entity codes are stored once.
+    texts: HashMap<String, u32>,
+}
+
+impl Writer {
+    fn offset(&self) -> Result<u32, CacheError> {
+        u32::try_from(self.out.len()).map_err(|_| {
+            let message = "dictionary too large for the mapped format";
+            CacheError::IO(io::Error::new(io::ErrorKind::InvalidData, message))
+        })
+    }
+
+    fn text(&mut self, text: &str, record: &mut Vec<u8>) -> Result<(), CacheError> {
+        let offset = match self.texts.get(text) {
+            Some(&offset) => offset,
+            None => {
+                let offset = self.offset()?;
+                self.out.extend_from_slice(text.as_bytes());
+                self.texts.insert(text.to_owned(), offset);
+                offset
+            }
+        };
+        put_u32(record, offset);
+        put_u32(record, text.len() as u32);
+        Ok(())
+    }
+
+    fn optional_text(
+        &mut self,
+        text: Option<&str>,
+        record: &mut Vec<u8>,
+    ) -> Result<(), CacheError> {
+        match text {
+            Some(text) => self.text(text, record),
+            None => {
+                put_u32(record, NONE);
+                put_u32(record, 0);
+                Ok(())
+            }
+        }
+    }
+
+    // Writes the records of `items` next to each other and a reference to
+    // them to `record`.
+    fn list<T, F>(&mut self, items: &[T], record: &mut Vec<u8>, write: F) -> Result<(), CacheError>
+    where
+        F: Fn(&mut Writer, &T, &mut Vec<u8>) -> Result<(), CacheError>,
+    {
+        let mut records = Vec::new();
+        for item in items {
+            write(self, item, &mut records)?;
+        }
+
+        let offset = self.offset()?;
+        self.out.extend_from_slice(&records);
+        put_u32(record, offset);
+        put_u32(record, items.len() as u32);
+        Ok(())
+    }
+}
+
+fn put_u32(out: &mut Vec<u8>, n: u32) {
+    out.extend_from_slice(&n.to_le_bytes());
+}
+
+fn write_entry(w: &mut Writer, e: &jmdict::Entry, record: &mut Vec<u8>) -> Result<(), CacheError> {
+    put_u32(record, e.seq);
+    w.list(&e.kanji, record, write_kanji)?;
+    w.list(&e.reading, record, write_reading)?;
+    w.list(&e.sense, record, write_sense)
+}
+
+fn write_kanji(w: &mut Writer, k: &jmdict::Kanji, record: &mut Vec<u8>) -> Result<(), CacheError> {
+    w.text(&k.text, record)?;
+    w.list(&k.info, record, |w, i, r| w.text(i.code(), r))?;
+    w.list(&k.pri_ref, record, write_pri_ref)
+}
+
+fn write_reading(
+    w: &mut Writer,
+    reading: &jmdict::Reading,
+    record: &mut Vec<u8>,
+) -> Result<(), CacheError> {
+    w.text(&reading.text, record)?;
+    put_u32(record, reading.no_kanji as u32);
+    w.list(&reading.pri_ref, record, write_pri_ref)?;
+    w.list(&reading.restrict, record, |w, t, r| w.text(t, r))?;
+    w.list(&reading.info, record, |w, i, r| w.text(i.code(), r))
+}
+
+fn write_pri_ref(_: &mut Writer, p: &PriRef, record: &mut Vec<u8>) -> Result<(), CacheError> {
+    put_u32(record, encode_pri_ref(p));
+    Ok(())
+}
+
+fn write_sense(w: &mut Writer, s: &jmdict::Sense, record: &mut Vec<u8>) -> Result<(), CacheError> {
+    w.list(&s.restrict_reading, record, |w, t, r| w.text(t, r))?;
+    w.list(&s.restrict_kanji, record, |w, t, r| w.text(t, r))?;
+    w.list(&s.cross_refs, record, |w, x, r| w.text(&x.to_string(), r))?;
+    w.list(&s.gloss, record, write_gloss)?;
+    w.list(&s.antonyms, record, |w, x, r| w.text(&x.to_string(), r))?;
+    w.list(&s.pos, record, |w, p, r| w.text(p.code(), r))?;
+    w.list(&s.fields, record, |w, f, r| w.text(f.code(), r))?;
+    w.list(&s.misc, record, |w, m, r| w.text(m.code(), r))?;
+    w.list(&s.source_lang, record, write_lsource)?;
+    w.list(&s.dialects, record, |w, d, r| w.text(d.code(), r))?;
+    w.list(&s.info, record, |w, t, r| w.text(t, r))
+}
+
+fn write_gloss(w: &mut Writer, g: &jmdict::Gloss, record: &mut Vec<u8>) -> Result<(), CacheError> {
+    w.optional_text(g.content.as_deref(), record)?;
+    record.extend_from_slice(g.lang.iso639_3().as_bytes());
+    record.push(0);
+    w.optional_text(g.gender.as_deref(), record)?;
+    w.optional_text(g.typ.as_deref(), record)
+}
+
+fn write_lsource(
+    w: &mut Writer,
+    l: &jmdict::LSource,
+    record: &mut Vec<u8>,
+) -> Result<(), CacheError> {
+    w.optional_text(l.content.as_deref(), record)?;
+    record.extend_from_slice(l.lang.iso639_3().as_bytes());
+    record.push(l.full as u8 | (l.wasei as u8) << 1);
+    Ok(())
+}
@@ -0,0 +1,186 @@
+//! Sorted indexes over kanji and reading text for prefix, suffix, wildcard
+//! and regular expression search.
+
+use regex::Regex;
+use std::collections::HashMap;
+
+/// Every distinct form text, sorted both as written and reversed so that
+/// prefix and suffix queries are binary searches. Each text maps to the
+/// indices of the entries it belongs to.
+#[derive(Debug, Default)]
+pub struct FormIndex {
+    texts: Vec<String>,
+    entries: Vec<Vec<usize>>,
+    // Texts with their characters reversed, with the position of the
+    // original in `texts`.
+    reversed: Vec<(String, usize)>,
+}
+
+impl FormIndex {
+    pub fn new(by_text: &HashMap<String, Vec<usize>>) -> Self {
+        let mut texts: Vec<_> = by_text.iter().collect();
+        texts.sort_unstable_by(|a, b| a.0.cmp(b.0));
+
+        let mut reversed: Vec<_> = texts
+            .iter()
+            .enumerate()
+            .map(|(i, (text, _))| (text.chars().rev().collect::<String>(), i))
+            .collect();
+        reversed.sort_unstable();
+
+        FormIndex {
+            entries: texts.iter().map(|(_, e)| (*e).clone()).collect(),
+            texts: texts.into_iter().map(|(t, _)| t.clone()).collect(),
+            reversed,
+        }
+    }
+
+    /// Indices of entries with a form starting with `prefix`.
+    pub fn prefix(&self, prefix: &str) -> Vec<usize> {
+        self.collect(self.prefix_range(prefix))
+    }
+
+    /// Indices of entries with a form ending with `suffix`.
+    pub fn suffix(&self, suffix: &str) -> Vec<usize> {
+        self.collect(self.suffix_range(suffix))
+    }
+
+    /// Indices of entries with a form matching a pattern in which `?`
+    /// stands for any one character and `*` for any number of characters.
+    pub fn glob(&self, pattern: &str) -> Vec<usize> {
+        let pattern: Vec<char> = pattern.chars().collect();
+        let is_wildcard = |c: &char| *c == '*' || *c == '?';
+
+        let first = pattern.iter().position(is_wildcard);
+        let last = pattern.iter().rposition(is_wildcard);
+        let (first, last) = match (first, last) {
+            (Some(first), Some(last)) => (first, last),
+            _ => {
+                let text: String = pattern.iter().collect();
+                return self.collect(self.prefix_range(&text).filter(|&i| self.texts[i] == text));
+            }
+        };
+
+        // Narrow the candidates by the literal text before the first or
+        // after the last wildcard, and by length if there is no `*`.
+        let prefix: String = pattern[..first].iter().collect();
+        let suffix: String = pattern[last + 1..].iter().collect();
+        let candidates: Box<dyn Iterator<Item = usize>> = if !prefix.is_empty() {
+            Box::new(self.prefix_range(&prefix))
+        } else if !suffix.is_empty() {
+            Box::new(self.suffix_range(&suffix))
+        } else {
+            Box::new(0..self.texts.len())
+        };
+        let fixed_len = !pattern.contains(&'*');
+
+        self.collect(candidates.filter(|&i| {
+            let text: Vec<char> = self.texts[i].chars().collect();
+            (!fixed_len || text.len() == pattern.len()) && glob_matches(&pattern, &text)
+        }))
+    }
+
+    /// Indices of entries with a form matching the regular expression
+    /// `pattern`. Expressions anchored with `^` and starting with literal
+    /// text only test forms with that prefix. Flags must be written inline,
+    /// e.g. `(?i)`, so that they are seen when finding the prefix.
+    pub fn regex(&self, pattern: &str) -> Result<Vec<usize>, regex::Error> {
+        let regex = Regex::new(pattern)?;
+        let prefix = literal_prefix(pattern);
+        Ok(self.collect(
+            self.prefix_range(&prefix)
+                .filter(|&i| regex.is_match(&self.texts[i])),
+        ))
+    }
+
+    fn prefix_range(&self, prefix: &str) -> std::ops::Range<usize> {
+        let start = self.texts.partition_point(|t| t.as_str() < prefix);
+        let len = self.texts[start..].partition_point(|t| t.starts_with(prefix));
+        start..start + len
+    }
+
+    fn suffix_range(&self, suffix: &str) -> impl Iterator<Item = usize> + '_ {
+        let reversed: String = suffix.chars().rev().collect();
+        let start = self
+            .reversed
+            .partition_point(|(t, _)| t.as_str() < reversed.as_str());
+        let len = self.reversed[start..].partition_point(|(t, _)| t.starts_with(&reversed));
+        self.reversed[start..start + len].iter().map(|&(_, i)| i)
+    }
+
+    fn collect(&self, texts: impl Iterator<Item = usize>) -> Vec<usize> {
+        let mut indices: Vec<_> = texts.flat_map(|i| &self.entries[i]).copied().collect();
+        indices.sort_unstable();
+        indices.dedup();
+        indices
+    }
+}
+
+// Wildcard matching with one point of backtracking: on a mismatch, the
+// last `*` seen takes one more character of the text.
+fn glob_matches(pattern: &[char], text: &[char]) -> bool {
+    let (mut p, mut t) = (0, 0);
+    let mut star = None;
+
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p, t));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => match star {
+                Some((star_p, star_t)) => {
+                    star = Some((star_p, star_t + 1));
+                    p = star_p + 1;
+                    t = star_t + 1;
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+// Literal text that every match of an expression anchored with `^` must
+// start with, e.g. "食べ" for "^食べ(る|た)".
+fn literal_prefix(regex: &str) -> String {
+    let rest = match regex.strip_prefix('^') {
+        Some(rest) => rest,
+        None => return String::new(),
+    };
+
+    let mut prefix: String = rest
+        .chars()
+        .take_while(|c| !r"\.+*?()|[]{}^$".contains(*c))
+        .collect();
+    // A quantifier applies to the last literal character only.
+    if matches!(rest[prefix.len()..].chars().next(), Some('?' | '*' | '{')) {
+        prefix.pop();
+    }
+    // Alternation outside of a group makes the prefix optional.
+    if has_top_level_alternation(rest) {
+        prefix.clear();
+    }
+    prefix
+}
+
+fn has_top_level_alternation(regex: &str) -> bool {
+    let mut depth = 0;
+    let mut chars = regex.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                chars.next();
+            }
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            '|' if depth == 0 => return true,
+            _ => {}
+        }
+    }
+    false
+}
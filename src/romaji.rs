@@ -0,0 +1,457 @@
+//! Conversion between romaji and kana.
+//!
+//! Input accepts Hepburn, Kunrei-shiki and Nihon-shiki spellings as well as
+//! the usual IME conventions: doubled consonants for っ, "n'" to separate ん
+//! from a following vowel, and macrons or circumflexes for long vowels.
+//! Output supports modified Hepburn, Kunrei-shiki, Nihon-shiki and passport
+//! Hepburn.
+
+use crate::kana;
+use std::collections::HashSet;
+
+#[rustfmt::skip]
+const SYLLABLES: &[(&str, &str)] = &[
+    ("a", "あ"), ("i", "い"), ("u", "う"), ("e", "え"), ("o", "お"),
+    ("ka", "か"), ("ki", "き"), ("ku", "く"), ("ke", "け"), ("ko", "こ"),
+    ("kya", "きゃ"), ("kyu", "きゅ"), ("kye", "きぇ"), ("kyo", "きょ"),
+    ("kwa", "くゎ"),
+    ("ga", "が"), ("gi", "ぎ"), ("gu", "ぐ"), ("ge", "げ"), ("go", "ご"),
+    ("gya", "ぎゃ"), ("gyu", "ぎゅ"), ("gye", "ぎぇ"), ("gyo", "ぎょ"),
+    ("gwa", "ぐゎ"),
+    ("sa", "さ"), ("si", "し"), ("shi", "し"), ("su", "す"), ("se", "せ"), ("so", "そ"),
+    ("sya", "しゃ"), ("syu", "しゅ"), ("sye", "しぇ"), ("syo", "しょ"),
+    ("sha", "しゃ"), ("shu", "しゅ"), ("she", "しぇ"), ("sho", "しょ"),
+    ("za", "ざ"), ("zi", "じ"), ("zu", "ず"), ("ze", "ぜ"), ("zo", "ぞ"),
+    ("zya", "じゃ"), ("zyu", "じゅ"), ("zye", "じぇ"), ("zyo", "じょ"),
+    ("ja", "じゃ"), ("ji", "じ"), ("ju", "じゅ"), ("je", "じぇ"), ("jo", "じょ"),
+    ("jya", "じゃ"), ("jyu", "じゅ"), ("jye", "じぇ"), ("jyo", "じょ"),
+    ("ta", "た"), ("ti", "ち"), ("chi", "ち"), ("tu", "つ"), ("tsu", "つ"),
+    ("te", "て"), ("to", "と"),
+    ("tya", "ちゃ"), ("tyu", "ちゅ"), ("tye", "ちぇ"), ("tyo", "ちょ"),
+    ("cha", "ちゃ"), ("chu", "ちゅ"), ("che", "ちぇ"), ("cho", "ちょ"),
+    ("tsa", "つぁ"), ("tsi", "つぃ"), ("tse", "つぇ"), ("tso", "つぉ"),
+    ("da", "だ"), ("di", "ぢ"), ("du", "づ"), ("dzu", "づ"), ("de", "で"), ("do", "ど"),
+    ("dya", "ぢゃ"), ("dyu", "ぢゅ"), ("dyo", "ぢょ"),
+    ("na", "な"), ("ni", "に"), ("nu", "ぬ"), ("ne", "ね"), ("no", "の"),
+    ("nya", "にゃ"), ("nyu", "にゅ"), ("nye", "にぇ"), ("nyo", "にょ"),
+    ("ha", "は"), ("hi", "ひ"), ("hu", "ふ"), ("fu", "ふ"), ("he", "へ"), ("ho", "ほ"),
+    ("hya", "ひゃ"), ("hyu", "ひゅ"), ("hye", "ひぇ"), ("hyo", "ひょ"),
+    ("fa", "ふぁ"), ("fi", "ふぃ"), ("fe", "ふぇ"), ("fo", "ふぉ"), ("fyu", "ふゅ"),
+    ("ba", "ば"), ("bi", "び"), ("bu", "ぶ"), ("be", "べ"), ("bo", "ぼ"),
+    ("bya", "びゃ"), ("byu", "びゅ"), ("bye", "びぇ"), ("byo", "びょ"),
+    ("pa", "ぱ"), ("pi", "ぴ"), ("pu", "ぷ"), ("pe", "ぺ"), ("po", "ぽ"),
+    ("pya", "ぴゃ"), ("pyu", "ぴゅ"), ("pye", "ぴぇ"), ("pyo", "ぴょ"),
+    ("ma", "ま"), ("mi", "み"), ("mu", "む"), ("me", "め"), ("mo", "も"),
+    ("mya", "みゃ"), ("myu", "みゅ"), ("mye", "みぇ"), ("myo", "みょ"),
+    ("ya", "や"), ("yu", "ゆ"), ("ye", "いぇ"), ("yo", "よ"),
+    ("ra", "ら"), ("ri", "り"), ("ru", "る"), ("re", "れ"), ("ro", "ろ"),
+    ("rya", "りゃ"), ("ryu", "りゅ"), ("rye", "りぇ"), ("ryo", "りょ"),
+    ("la", "ら"), ("li", "り"), ("lu", "る"), ("le", "れ"), ("lo", "ろ"),
+    ("wa", "わ"), ("wi", "うぃ"), ("we", "うぇ"), ("wo", "を"),
+    ("va", "ゔぁ"), ("vi", "ゔぃ"), ("vu", "ゔ"), ("ve", "ゔぇ"), ("vo", "ゔぉ"),
+    ("thi", "てぃ"), ("dhi", "でぃ"), ("twu", "とぅ"), ("dwu", "どぅ"),
+    ("xa", "ぁ"), ("xi", "ぃ"), ("xu", "ぅ"), ("xe", "ぇ"), ("xo", "ぉ"),
+    ("xya", "ゃ"), ("xyu", "ゅ"), ("xyo", "ょ"), ("xtu", "っ"), ("xtsu", "っ"),
+    ("xwa", "ゎ"), ("xka", "ゕ"), ("xke", "ゖ"),
+    ("n'", "ん"), ("-", "ー"),
+];
+
+/// Converts romaji to hiragana. Characters that are not part of a romaji
+/// syllable are copied through unchanged. Long vowels written with a macron
+/// or circumflex become the vowel doubled, except for ō and ē which are
+/// spelled おう and えい as is most common; see [`to_hiragana_candidates`]
+/// for the alternatives.
+pub fn to_hiragana(romaji: &str) -> String {
+    let chars: Vec<char> = romaji
+        .to_lowercase()
+        .chars()
+        .flat_map(expand_long_vowel)
+        .collect();
+    let mut kana = String::with_capacity(romaji.len() * 3);
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        let next = chars.get(i + 1).copied();
+
+        // A syllabic n is written before consonants and at the end of a word,
+        // and as "m" before labials in traditional Hepburn: "shimbun",
+        // "semmon". Checked first so that "mm" is not read as a geminate.
+        let syllabic_n = match (c, next) {
+            ('n', None) => true,
+            ('n', Some(n)) => n != '\'' && !is_vowel(n) && n != 'y',
+            ('m', Some(n)) => n == 'b' || n == 'p' || n == 'm',
+            _ => false,
+        };
+        if syllabic_n {
+            kana.push('ん');
+            // As in IMEs, "nn" before a consonant or at the end of a word is
+            // a single ん: "honn" -> ほん, but "kinnen" -> きんねん.
+            let after = chars.get(i + 2).copied();
+            if c == 'n' && next == Some('n') && !after.is_some_and(|a| is_vowel(a) || a == 'y') {
+                i += 1;
+            }
+            i += 1;
+            continue;
+        }
+
+        // A consonant doubled with itself, or "tch" in Hepburn, marks a
+        // geminate: "kitte" -> きって, "matcha" -> まっちゃ.
+        let geminate = match next {
+            Some(n) if n == c && is_consonant(c) && c != 'n' => true,
+            Some('c') => c == 't' && chars.get(i + 2) == Some(&'h'),
+            _ => false,
+        };
+        if geminate {
+            kana.push('っ');
+            i += 1;
+            continue;
+        }
+
+        let matched = (1..=4).rev().find_map(|len| {
+            if i + len > chars.len() {
+                return None;
+            }
+            let part: String = chars[i..i + len].iter().collect();
+            SYLLABLES
+                .iter()
+                .find(|(r, _)| *r == part)
+                .map(|(_, k)| (len, *k))
+        });
+
+        match matched {
+            Some((len, k)) => {
+                kana.push_str(k);
+                i += len;
+            }
+            None => {
+                kana.push(c);
+                i += 1;
+            }
+        }
+    }
+
+    kana
+}
+
+/// Every hiragana spelling the romaji may stand for, differing in how
+/// ambiguous long vowels are resolved: "ō" may be おう or おお, and "ē" may be
+/// えい or ええ. Only the first [`MAX_AMBIGUOUS_VOWELS`] such vowels are
+/// expanded both ways; later ones take the spelling [`to_hiragana`] uses.
+pub fn to_hiragana_candidates(romaji: &str) -> Vec<String> {
+    let mut spellings = vec![String::new()];
+    let mut ambiguous = 0;
+
+    for c in romaji.to_lowercase().chars() {
+        let options: &[&str] = match c {
+            'ō' | 'ô' if ambiguous < MAX_AMBIGUOUS_VOWELS => &["ou", "oo"],
+            'ē' | 'ê' if ambiguous < MAX_AMBIGUOUS_VOWELS => &["ei", "ee"],
+            _ => &[""],
+        };
+        if options.len() > 1 {
+            ambiguous += 1;
+        }
+        spellings = spellings
+            .iter()
+            .flat_map(|s| {
+                options.iter().map(move |o| {
+                    let mut s = s.clone();
+                    if o.is_empty() {
+                        s.push(c);
+                    } else {
+                        s.push_str(o);
+                    }
+                    s
+                })
+            })
+            .collect();
+    }
+
+    let mut seen = HashSet::new();
+    spellings
+        .iter()
+        .map(|s| to_hiragana(s))
+        .filter(|kana| seen.insert(kana.clone()))
+        .collect()
+}
+
+/// Limit on the ambiguous long vowels expanded by
+/// [`to_hiragana_candidates`], which yields up to 2^n candidates for n of
+/// them.
+pub const MAX_AMBIGUOUS_VOWELS: usize = 6;
+
+fn expand_long_vowel(c: char) -> Vec<char> {
+    match c {
+        'ā' | 'â' => vec!['a', 'a'],
+        'ī' | 'î' => vec!['i', 'i'],
+        'ū' | 'û' => vec!['u', 'u'],
+        'ē' | 'ê' => vec!['e', 'i'],
+        'ō' | 'ô' => vec!['o', 'u'],
+        c => vec![c],
+    }
+}
+
+fn is_vowel(c: char) -> bool {
+    matches!(c, 'a' | 'i' | 'u' | 'e' | 'o')
+}
+
+fn is_consonant(c: char) -> bool {
+    c.is_ascii_lowercase() && !is_vowel(c)
+}
+
+/// Romanization system used by [`romanize`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum System {
+    // Modified Hepburn: shi, chi, tsu, fu, ji; "n'" before vowels and y.
+    Hepburn,
+    // Kunrei-shiki: si, ti, tu, hu, zi, sya.
+    Kunrei,
+    // Nihon-shiki: as Kunrei-shiki, but keeps di, du and wo for ぢ, づ, を.
+    Nihon,
+    // Hepburn as used in Japanese passports: long vowels are not marked,
+    // and ん becomes m before b, m and p.
+    Passport,
+}
+
+/// How long vowels are written by [`romanize`]. Ignored by
+/// [`System::Passport`], which does not mark them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LongVowels {
+    // "tōkyō", "kōhī"
+    Macrons,
+    // "tôkyô", "kôhî", as preferred by Kunrei-shiki
+    Circumflexes,
+    // "toukyou", "koohii"
+    Doubled,
+}
+
+#[derive(Debug, Clone)]
+pub struct RomanizeOptions {
+    pub system: System,
+    pub long_vowels: LongVowels,
+    // Whether おう is written as a long o. Kana carry no word boundaries, so
+    // this also joins お and a verb-ending う: おもう becomes "omō" where
+    // Hepburn writes "omou". Turn it off when romanizing verbs.
+    pub merge_ou: bool,
+}
+
+impl Default for RomanizeOptions {
+    fn default() -> Self {
+        RomanizeOptions {
+            system: System::Hepburn,
+            long_vowels: LongVowels::Macrons,
+            merge_ou: true,
+        }
+    }
+}
+
+// Kana whose spelling differs between systems: (kana, Hepburn, Kunrei, Nihon).
+#[rustfmt::skip]
+const IRREGULAR: &[(char, &str, &str, &str)] = &[
+    ('し', "shi", "si", "si"), ('ち', "chi", "ti", "ti"), ('つ', "tsu", "tu", "tu"),
+    ('ふ', "fu", "hu", "hu"), ('じ', "ji", "zi", "zi"), ('ぢ', "ji", "zi", "di"),
+    ('づ', "zu", "zu", "du"), ('を', "o", "o", "wo"), ('ゐ', "i", "i", "wi"),
+    ('ゑ', "e", "e", "we"),
+];
+
+#[rustfmt::skip]
+const REGULAR: &[(char, &str)] = &[
+    ('あ', "a"), ('い', "i"), ('う', "u"), ('え', "e"), ('お', "o"),
+    ('か', "ka"), ('き', "ki"), ('く', "ku"), ('け', "ke"), ('こ', "ko"),
+    ('が', "ga"), ('ぎ', "gi"), ('ぐ', "gu"), ('げ', "ge"), ('ご', "go"),
+    ('さ', "sa"), ('す', "su"), ('せ', "se"), ('そ', "so"),
+    ('ざ', "za"), ('ず', "zu"), ('ぜ', "ze"), ('ぞ', "zo"),
+    ('た', "ta"), ('て', "te"), ('と', "to"),
+    ('だ', "da"), ('で', "de"), ('ど', "do"),
+    ('な', "na"), ('に', "ni"), ('ぬ', "nu"), ('ね', "ne"), ('の', "no"),
+    ('は', "ha"), ('ひ', "hi"), ('へ', "he"), ('ほ', "ho"),
+    ('ば', "ba"), ('び', "bi"), ('ぶ', "bu"), ('べ', "be"), ('ぼ', "bo"),
+    ('ぱ', "pa"), ('ぴ', "pi"), ('ぷ', "pu"), ('ぺ', "pe"), ('ぽ', "po"),
+    ('ま', "ma"), ('み', "mi"), ('む', "mu"), ('め', "me"), ('も', "mo"),
+    ('や', "ya"), ('ゆ', "yu"), ('よ', "yo"),
+    ('ら', "ra"), ('り', "ri"), ('る', "ru"), ('れ', "re"), ('ろ', "ro"),
+    ('わ', "wa"), ('ゔ', "vu"),
+    ('ぁ', "a"), ('ぃ', "i"), ('ぅ', "u"), ('ぇ', "e"), ('ぉ', "o"),
+    ('ゃ', "ya"), ('ゅ', "yu"), ('ょ', "yo"), ('ゎ', "wa"),
+];
+
+/// Converts kana in `text` to Latin script. Characters other than kana,
+/// such as kanji, are copied through unchanged.
+pub fn romanize(text: &str, options: &RomanizeOptions) -> String {
+    let chars: Vec<char> = kana::to_hiragana(&kana::to_full_width(text))
+        .chars()
+        .collect();
+    let mut romaji = String::with_capacity(text.len());
+    let mut geminate = false;
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+
+        match c {
+            'っ' => {
+                geminate = true;
+                i += 1;
+                continue;
+            }
+            'ー' => {
+                extend_vowel(&mut romaji, None, options);
+                i += 1;
+                continue;
+            }
+            'ん' => {
+                romaji.push_str(&syllabic_n(chars.get(i + 1).copied(), options));
+                i += 1;
+                continue;
+            }
+            _ => {}
+        }
+
+        let (syllable, len) = match syllable(&chars[i..], options.system) {
+            Some(s) => s,
+            None => {
+                romaji.push(punctuation(c).unwrap_or(c));
+                geminate = false;
+                i += 1;
+                continue;
+            }
+        };
+        i += len;
+
+        if geminate {
+            geminate = false;
+            match syllable.chars().next() {
+                // Hepburn writes っち as "tchi".
+                Some('c') => romaji.push('t'),
+                Some(first) if !is_vowel(first) => romaji.push(first),
+                _ => {}
+            }
+        }
+
+        let vowel = match c {
+            'あ' | 'い' | 'う' | 'え' | 'お' if len == 1 => syllable.chars().next(),
+            _ => None,
+        };
+        if let Some(v) = vowel.filter(|&v| extends_vowel(&romaji, v, options)) {
+            extend_vowel(&mut romaji, Some(v), options);
+        } else {
+            romaji.push_str(&syllable);
+        }
+    }
+
+    romaji
+}
+
+fn syllable(chars: &[char], system: System) -> Option<(String, usize)> {
+    let base = base_syllable(chars[0], system)?;
+
+    let small = match chars.get(1) {
+        Some(&s) if "ゃゅょぁぃぅぇぉ".contains(s) => s,
+        _ => return Some((base, 1)),
+    };
+    let small_vowel = base_syllable(small, system)?.chars().last()?;
+    // Build combined sounds from the Hepburn stem for kana without a
+    // standard spelling in the other systems, such as ふぁ or てぃ.
+    let hepburn = base_syllable(chars[0], System::Hepburn)?;
+
+    let combined = match (chars[0], small) {
+        (_, 'ゃ') | (_, 'ゅ') | (_, 'ょ') | (_, 'ぇ') if base.ends_with('i') && base.len() > 1 =>
+        {
+            let stem = &base[..base.len() - 1];
+            if system == System::Hepburn || system == System::Passport {
+                if stem.ends_with('h') || stem == "j" {
+                    format!("{}{}", stem, small_vowel)
+                } else {
+                    format!("{}y{}", stem, small_vowel)
+                }
+            } else {
+                format!("{}y{}", stem, small_vowel)
+            }
+        }
+        ('う', _) => format!("w{}", small_vowel),
+        (_, 'ゃ') | (_, 'ゅ') | (_, 'ょ') => return Some((base, 1)),
+        _ => format!("{}{}", &hepburn[..hepburn.len() - 1], small_vowel),
+    };
+
+    Some((combined, 2))
+}
+
+fn base_syllable(c: char, system: System) -> Option<String> {
+    if let Some(&(_, hepburn, kunrei, nihon)) = IRREGULAR.iter().find(|(k, ..)| *k == c) {
+        let s = match system {
+            System::Hepburn | System::Passport => hepburn,
+            System::Kunrei => kunrei,
+            System::Nihon => nihon,
+        };
+        return Some(s.to_owned());
+    }
+
+    REGULAR
+        .iter()
+        .find(|(k, _)| *k == c)
+        .map(|(_, r)| (*r).to_owned())
+}
+
+fn syllabic_n(next: Option<char>, options: &RomanizeOptions) -> String {
+    let next = next.and_then(|n| base_syllable(n, options.system));
+    let next_start = next.as_ref().and_then(|n| n.chars().next());
+
+    match (options.system, next_start) {
+        (System::Passport, Some('b'))
+        | (System::Passport, Some('m'))
+        | (System::Passport, Some('p')) => "m".to_owned(),
+        (System::Passport, _) => "n".to_owned(),
+        (_, Some(n)) if is_vowel(n) || n == 'y' => "n'".to_owned(),
+        _ => "n".to_owned(),
+    }
+}
+
+// Whether a vowel kana following the romaji so far lengthens its last
+// vowel: aa, uu, ee, oo and, unless disabled, ou. Hepburn keeps ii and ei
+// as written.
+fn extends_vowel(romaji: &str, vowel: char, options: &RomanizeOptions) -> bool {
+    match (romaji.chars().last(), vowel) {
+        (Some(last), v) if last == v => v != 'i',
+        (Some('o'), 'u') => options.merge_ou,
+        _ => false,
+    }
+}
+
+fn extend_vowel(romaji: &mut String, written: Option<char>, options: &RomanizeOptions) {
+    let last = match romaji.chars().last() {
+        Some(c) if is_vowel(c) => c,
+        _ => {
+            romaji.extend(written);
+            return;
+        }
+    };
+
+    if options.system == System::Passport {
+        return;
+    }
+
+    match options.long_vowels {
+        LongVowels::Doubled => romaji.push(written.unwrap_or(last)),
+        LongVowels::Macrons | LongVowels::Circumflexes => {
+            let marks = if options.long_vowels == LongVowels::Macrons {
+                "āīūēō"
+            } else {
+                "âîûêô"
+            };
+            let index = "aiueo".find(last).unwrap_or(0);
+            romaji.pop();
+            romaji.extend(marks.chars().nth(index));
+        }
+    }
+}
+
+fn punctuation(c: char) -> Option<char> {
+    match c {
+        '。' => Some('.'),
+        '、' => Some(','),
+        '？' => Some('?'),
+        '！' => Some('!'),
+        '・' | '　' => Some(' '),
+        _ => None,
+    }
+}
@@ -0,0 +1,149 @@
+//! Matching shared by the searches of [`JMDict`](crate::jmdict::JMDict),
+//! [`MappedJMDict`](crate::mapped::MappedJMDict) and
+//! [`JMnedict`](crate::jmnedict::JMnedict), written once against the parts
+//! of an entry all of them can provide.
+
+use crate::deinflect::{self, Deinflection};
+use crate::entities::PartOfSpeech;
+use crate::jmdict;
+use crate::jmnedict;
+use crate::kana::{self, NormalizeOptions};
+use crate::mapped;
+use crate::romaji;
+use std::collections::HashSet;
+
+/// The parts of an entry the searches look at.
+pub(crate) trait Headwords {
+    fn kanji_texts(&self) -> impl Iterator<Item = &str>;
+    fn reading_texts(&self) -> impl Iterator<Item = &str>;
+    fn has_pos<F: Fn(&PartOfSpeech) -> bool>(&self, predicate: F) -> bool;
+}
+
+impl Headwords for jmdict::Entry {
+    fn kanji_texts(&self) -> impl Iterator<Item = &str> {
+        self.kanji.iter().map(|k| k.text.as_str())
+    }
+
+    fn reading_texts(&self) -> impl Iterator<Item = &str> {
+        self.reading.iter().map(|r| r.text.as_str())
+    }
+
+    fn has_pos<F: Fn(&PartOfSpeech) -> bool>(&self, predicate: F) -> bool {
+        self.sense.iter().flat_map(|s| &s.pos).any(predicate)
+    }
+}
+
+impl Headwords for mapped::Entry<'_> {
+    fn kanji_texts(&self) -> impl Iterator<Item = &str> {
+        self.kanji().map(|k| k.text())
+    }
+
+    fn reading_texts(&self) -> impl Iterator<Item = &str> {
+        self.reading().map(|r| r.text())
+    }
+
+    fn has_pos<F: Fn(&PartOfSpeech) -> bool>(&self, predicate: F) -> bool {
+        self.sense().flat_map(|s| s.pos()).any(|p| predicate(&p))
+    }
+}
+
+// Names have no parts of speech.
+impl Headwords for jmnedict::Entry {
+    fn kanji_texts(&self) -> impl Iterator<Item = &str> {
+        self.kanji.iter().map(|k| k.text.as_str())
+    }
+
+    fn reading_texts(&self) -> impl Iterator<Item = &str> {
+        self.reading.iter().map(|r| r.text.as_str())
+    }
+
+    fn has_pos<F: Fn(&PartOfSpeech) -> bool>(&self, _predicate: F) -> bool {
+        false
+    }
+}
+
+impl<T: Headwords> Headwords for &T {
+    fn kanji_texts(&self) -> impl Iterator<Item = &str> {
+        (**self).kanji_texts()
+    }
+
+    fn reading_texts(&self) -> impl Iterator<Item = &str> {
+        (**self).reading_texts()
+    }
+
+    fn has_pos<F: Fn(&PartOfSpeech) -> bool>(&self, predicate: F) -> bool {
+        (**self).has_pos(predicate)
+    }
+}
+
+/// Entries with a kanji or reading equal to `phrase` once both are
+/// normalized.
+pub(crate) fn normalized<E: Headwords>(
+    entries: impl Iterator<Item = E>,
+    phrase: &str,
+    options: &NormalizeOptions,
+) -> Vec<E> {
+    let phrase = kana::normalize(phrase, options);
+    entries
+        .filter(|e| {
+            e.reading_texts()
+                .chain(e.kanji_texts())
+                .any(|t| kana::normalize(t, options) == phrase)
+        })
+        .collect()
+}
+
+/// Entries with a reading matching one of the kana spellings of `romaji`.
+/// Long vowel marks are expanded on both sides.
+pub(crate) fn romaji<E: Headwords>(entries: impl Iterator<Item = E>, romaji: &str) -> Vec<E> {
+    let options = NormalizeOptions {
+        expand_long_vowels: true,
+        ..NormalizeOptions::default()
+    };
+    let candidates: HashSet<_> = romaji::to_hiragana_candidates(romaji)
+        .iter()
+        .map(|c| kana::normalize(c, &options))
+        .collect();
+
+    entries
+        .filter(|e| {
+            e.reading_texts()
+                .any(|r| candidates.contains(&kana::normalize(r, &options)))
+        })
+        .collect()
+}
+
+/// Entries that a deinflection of `word` leads to, each with the first
+/// such deinflection, ordered as the deinflections are.
+pub(crate) fn deinflected<E: Headwords>(
+    entries: impl Iterator<Item = E>,
+    word: &str,
+) -> Vec<(E, Deinflection)> {
+    let candidates = deinflect::deinflect(word);
+    let mut matches = Vec::new();
+
+    for e in entries {
+        let found = candidates.iter().position(|d| {
+            class_matches(d, &e)
+                && d.lookup_terms()
+                    .iter()
+                    .any(|&t| e.kanji_texts().chain(e.reading_texts()).any(|x| x == t))
+        });
+        if let Some(i) = found {
+            matches.push((i, e));
+        }
+    }
+
+    matches.sort_by_key(|&(i, _)| i);
+    matches
+        .into_iter()
+        .map(|(i, e)| (e, candidates[i].clone()))
+        .collect()
+}
+
+/// Whether the entry has a part of speech that inflects as the class the
+/// deinflection arrived at.
+pub(crate) fn class_matches<E: Headwords>(d: &Deinflection, entry: &E) -> bool {
+    d.class
+        .is_none_or(|class| entry.has_pos(|p| class.matches(p)))
+}
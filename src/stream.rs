@@ -0,0 +1,259 @@
+use crate::errors::{ParseError, XmlError};
+use std::collections::HashMap;
+use std::io::BufRead;
+
+/// Pulls individual elements out of an XML document without building a DOM
+/// for the whole file.
+///
+/// Markup outside of the requested elements is skipped, except for the
+/// document type declaration, whose general entity declarations are recorded
+/// and substituted in every element returned.
+pub struct ElementReader<R> {
+    reader: R,
+    entities: HashMap<String, String>,
+    keep_entity_names: bool,
+    done: bool,
+}
+
+impl<R: BufRead> ElementReader<R> {
+    pub fn new(reader: R) -> Self {
+        ElementReader {
+            reader,
+            entities: HashMap::new(),
+            keep_entity_names: false,
+            done: false,
+        }
+    }
+
+    /// Replaces references to declared entities with the entity name rather
+    /// than its value, e.g. `&v5k;` becomes `v5k`.
+    pub fn keep_entity_names(mut self) -> Self {
+        self.keep_entity_names = true;
+        self
+    }
+
+    /// Reads forward to the next element with the given tag name and returns
+    /// its complete markup, or `None` once the end of the input is reached.
+    pub fn next_element(&mut self, tag: &str) -> Result<Option<String>, ParseError> {
+        if self.done {
+            return Ok(None);
+        }
+
+        let result = self.read_element(tag);
+        if !matches!(result, Ok(Some(_))) {
+            self.done = true;
+        }
+        result
+    }
+
+    fn read_element(&mut self, tag: &str) -> Result<Option<String>, ParseError> {
+        let mut buf = Vec::new();
+        loop {
+            buf.clear();
+            let start = match read_markup(&mut self.reader, &mut buf)? {
+                Some(start) => start,
+                None => return Ok(None),
+            };
+            let markup = &buf[start..];
+
+            if markup.starts_with(b"<!DOCTYPE") {
+                let doctype = String::from_utf8(markup.to_vec())?;
+                parse_entity_decls(&doctype, &mut self.entities);
+                continue;
+            }
+
+            if !is_start_tag(markup, tag) {
+                continue;
+            }
+
+            let mut element = markup.to_vec();
+            let mut depth = if markup.ends_with(b"/>") { 0 } else { 1 };
+            while depth > 0 {
+                let start = read_markup(&mut self.reader, &mut element)?
+                    .ok_or_else(|| XmlError::UnexpectedEof(tag.to_owned()))?;
+                let markup = &element[start..];
+                if is_start_tag(markup, tag) && !markup.ends_with(b"/>") {
+                    depth += 1;
+                } else if is_end_tag(markup, tag) {
+                    depth -= 1;
+                }
+            }
+
+            let element = String::from_utf8(element)?;
+            return Ok(Some(expand_entities(
+                &element,
+                &self.entities,
+                self.keep_entity_names,
+            )));
+        }
+    }
+}
+
+/// Appends the text up to and including the next complete piece of markup
+/// to `out`, returning the offset at which the markup begins.
+fn read_markup<R: BufRead>(reader: &mut R, out: &mut Vec<u8>) -> Result<Option<usize>, ParseError> {
+    if reader.read_until(b'<', out)? == 0 || out.last() != Some(&b'<') {
+        return Ok(None);
+    }
+
+    let start = out.len() - 1;
+    loop {
+        if reader.read_until(b'>', out)? == 0 || out.last() != Some(&b'>') {
+            return Err(
+                XmlError::UnexpectedEof(String::from_utf8_lossy(&out[start..]).into()).into(),
+            );
+        }
+
+        if markup_complete(&out[start..]) {
+            return Ok(Some(start));
+        }
+    }
+}
+
+fn markup_complete(m: &[u8]) -> bool {
+    if m.starts_with(b"<!--") {
+        return m.len() >= 7 && m.ends_with(b"-->");
+    }
+    if m.starts_with(b"<![CDATA[") {
+        return m.ends_with(b"]]>");
+    }
+    if m.starts_with(b"<?") {
+        return m.ends_with(b"?>");
+    }
+
+    // Tags and declarations end at the first '>' outside of quoted values,
+    // bracketed internal subsets and comments.
+    let mut quote = None;
+    let mut depth = 0;
+    let mut i = 1;
+    while i < m.len() {
+        let b = m[i];
+        match quote {
+            Some(q) if b == q => quote = None,
+            Some(_) => {}
+            None => match b {
+                b'"' | b'\'' => quote = Some(b),
+                b'[' => depth += 1,
+                b']' => depth -= 1,
+                b'<' if m[i..].starts_with(b"<!--") => match find(&m[i + 4..], b"-->") {
+                    Some(end) => {
+                        i += 4 + end + 3;
+                        continue;
+                    }
+                    None => return false,
+                },
+                _ => {}
+            },
+        }
+        i += 1;
+    }
+
+    quote.is_none() && depth == 0
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|w| w == needle)
+}
+
+fn is_start_tag(m: &[u8], tag: &str) -> bool {
+    m.len() > tag.len() + 1 && &m[1..=tag.len()] == tag.as_bytes() && is_name_end(m[tag.len() + 1])
+}
+
+fn is_end_tag(m: &[u8], tag: &str) -> bool {
+    m.starts_with(b"</")
+        && m.len() > tag.len() + 2
+        && &m[2..tag.len() + 2] == tag.as_bytes()
+        && is_name_end(m[tag.len() + 2])
+}
+
+fn is_name_end(b: u8) -> bool {
+    b == b'>' || b == b'/' || b.is_ascii_whitespace()
+}
+
+fn parse_entity_decls(doctype: &str, entities: &mut HashMap<String, String>) {
+    let mut rest = doctype;
+    while let Some(i) = rest.find("<!") {
+        rest = &rest[i..];
+
+        if rest.starts_with("<!--") {
+            match rest.find("-->") {
+                Some(end) => rest = &rest[end + 3..],
+                None => return,
+            }
+            continue;
+        }
+
+        if let Some(decl) = rest.strip_prefix("<!ENTITY") {
+            let decl = decl.trim_start();
+            if !decl.starts_with('%') {
+                let name_end = decl.find(|c: char| c.is_whitespace()).unwrap_or(decl.len());
+                let (name, value) = decl.split_at(name_end);
+                let value = value.trim_start();
+
+                if let Some(q) = value.chars().next().filter(|&q| q == '"' || q == '\'') {
+                    if let Some(end) = value[1..].find(q) {
+                        entities.insert(name.to_owned(), value[1..=end].to_owned());
+                    }
+                }
+            }
+        }
+
+        rest = &rest[2..];
+    }
+}
+
+fn expand_entities(text: &str, entities: &HashMap<String, String>, keep_names: bool) -> String {
+    if entities.is_empty() {
+        return text.to_owned();
+    }
+
+    let mut expanded = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(i) = rest.find('&') {
+        expanded.push_str(&rest[..i]);
+        rest = &rest[i..];
+
+        let value = rest
+            .find(';')
+            .and_then(|end| entities.get_key_value(&rest[1..end]).map(|v| (end, v)));
+        match value {
+            Some((end, (name, value))) => {
+                let value = if keep_names { name } else { value };
+                escape_entity_value(value, &mut expanded);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                expanded.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    expanded.push_str(rest);
+
+    expanded
+}
+
+/// Escapes markup in an entity value, keeping character references such as
+/// `&#38;` as they are so the XML parser still resolves them.
+fn escape_entity_value(value: &str, out: &mut String) {
+    for (i, c) in value.char_indices() {
+        match c {
+            '&' if is_char_ref(&value[i..]) => out.push('&'),
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            c => out.push(c),
+        }
+    }
+}
+
+fn is_char_ref(text: &str) -> bool {
+    let body = match text.strip_prefix("&#").and_then(|t| t.split(';').next()) {
+        Some(body) if text.len() > body.len() + 2 => body,
+        _ => return false,
+    };
+    match body.strip_prefix('x') {
+        Some(hex) => !hex.is_empty() && hex.chars().all(|c| c.is_ascii_hexdigit()),
+        None => !body.is_empty() && body.chars().all(|c| c.is_ascii_digit()),
+    }
+}
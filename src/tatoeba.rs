@@ -1,15 +1,33 @@
 use crate::errors::ParseError;
+use crate::language::Language;
+use crate::romaji;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
 use std::fs::File;
 use std::io::{BufRead, BufReader};
 use std::path::Path;
 
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Tatoeba {
     pub entries: Vec<Sentence>,
 }
 
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Sentence {
     pub content: String,
-    pub language: String,
+    // `None` for sentences whose language has not been set, written as
+    // "\N" in the export.
+    pub language: Option<Language>,
+}
+
+const UNKNOWN_LANGUAGE: &str = "\\N";
+
+impl Sentence {
+    /// The sentence with its kana written in Latin script. Kanji are left
+    /// as they are.
+    pub fn romanize(&self, options: &romaji::RomanizeOptions) -> String {
+        romaji::romanize(&self.content, options)
+    }
 }
 
 impl Tatoeba {
@@ -20,6 +38,13 @@ impl Tatoeba {
             .collect()
     }
 
+    pub fn filter_language(&self, language: Language) -> Vec<&Sentence> {
+        self.entries
+            .iter()
+            .filter(|s| s.language == Some(language))
+            .collect()
+    }
+
     pub fn from_file_jp<P: AsRef<Path>>(filepath: P) -> Result<Self, ParseError> {
         Tatoeba::from_file(filepath, Some(|s: &str| s == "jpn"))
     }
@@ -32,7 +57,19 @@ impl Tatoeba {
         F: Fn(&str) -> bool,
     {
         let file = File::open(filepath)?;
-        let reader = BufReader::new(file);
+        Tatoeba::from_reader(BufReader::new(file), language_filter)
+    }
+
+    /// Reads sentences from tab-separated lines of sequence number,
+    /// language code and text, keeping those whose language code passes the
+    /// filter, if any.
+    pub fn from_reader<F, R: BufRead>(
+        reader: R,
+        language_filter: Option<F>,
+    ) -> Result<Self, ParseError>
+    where
+        F: Fn(&str) -> bool,
+    {
         let mut entries = Vec::new();
 
         let language_filter: Box<dyn Fn(&str) -> bool> = match language_filter {
@@ -55,8 +92,12 @@ impl Tatoeba {
                 "sentence value not found".to_owned(),
             ))?;
 
+            let language = match language {
+                UNKNOWN_LANGUAGE => None,
+                code => Some(Language::from_iso639_3(code)?),
+            };
             entries.push(Sentence {
-                language: language.to_owned(),
+                language,
                 content: content.to_owned(),
             });
         }
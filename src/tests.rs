@@ -1,5 +1,10 @@
-use crate::jmdict::JMDict;
+use crate::entities::{Dialect, Field, KanjiInfo, Misc, PartOfSpeech, ReadingInfo};
+use crate::jmdict::{JMDict, ParseOptions, PriRef, XRef};
+use crate::kana::{self, NormalizeOptions};
 use crate::kanjidic::Kanjidic;
+use crate::language::Language;
+use crate::romaji;
+use crate::stream::ElementReader;
 use crate::tatoeba::Tatoeba;
 use std::env;
 
@@ -44,3 +49,1381 @@ fn tatoeba_works() {
     let dict = Tatoeba::from_file_jp(sentences_path).unwrap();
     let _result: Vec<_> = dict.filter_substring("心");
 }
+
+const JMDICT_SAMPLE: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE JMdict [
+<!ELEMENT JMdict (entry*)>
+<!-- "entry" elements hold the kanji, reading and sense elements -->
+<!ENTITY n "noun (common) (futsuumeishi)">
+<!ENTITY v1 "Ichidan verb">
+<!ENTITY vt "transitive verb">
+<!ENTITY uk "word usually written using kana alone">
+<!ENTITY sK "search-only kanji form">
+<!ENTITY ik "word containing irregular kana usage">
+]>
+<JMdict>
+<!-- JMdict created: 2020-06-01 -->
+<entry>
+<ent_seq>1358280</ent_seq>
+<k_ele>
+<keb>食べる</keb>
+<ke_pri>ichi1</ke_pri>
+<ke_pri>news2</ke_pri>
+<ke_pri>nf25</ke_pri>
+</k_ele>
+<k_ele>
+<keb>喰べる</keb>
+<ke_inf>&sK;</ke_inf>
+</k_ele>
+<r_ele>
+<reb>たべる</reb>
+<re_pri>ichi1</re_pri>
+<re_pri>news2</re_pri>
+<re_pri>nf25</re_pri>
+</r_ele>
+<sense>
+<pos>&v1;</pos>
+<pos>&vt;</pos>
+<gloss>to eat</gloss>
+<gloss xml:lang="ger">essen</gloss>
+</sense>
+</entry>
+<entry>
+<ent_seq>1579470</ent_seq>
+<k_ele>
+<keb>今日</keb>
+</k_ele>
+<k_ele>
+<keb>今日は</keb>
+</k_ele>
+<r_ele>
+<reb>きょう</reb>
+<re_restr>今日</re_restr>
+</r_ele>
+<r_ele>
+<reb>こんにち</reb>
+</r_ele>
+<sense>
+<stagr>きょう</stagr>
+<pos>&n;</pos>
+<gloss>today</gloss>
+</sense>
+<sense>
+<stagk>今日</stagk>
+<stagr>こんにち</stagr>
+<pos>&n;</pos>
+<gloss>these days</gloss>
+</sense>
+<sense>
+<stagk>今日は</stagk>
+<gloss>hello</gloss>
+</sense>
+</entry>
+<entry>
+<ent_seq>1044840</ent_seq>
+<r_ele>
+<reb>コーヒー</reb>
+</r_ele>
+<r_ele>
+<reb>コーヒ</reb>
+<re_nokanji/>
+<re_inf>&ik;</re_inf>
+</r_ele>
+<sense>
+<pos>&n;</pos>
+<misc>&uk;</misc>
+<lsource xml:lang="dut">koffie</lsource>
+<gloss>coffee</gloss>
+</sense>
+</entry>
+</JMdict>
+"#;
+
+fn sample_jmdict() -> JMDict {
+    let entries = JMDict::stream(JMDICT_SAMPLE.as_bytes())
+        .collect::<Result<_, _>>()
+        .unwrap();
+    JMDict { entries }
+}
+
+#[test]
+fn jmdict_stream_works() {
+    let entries: Vec<_> = JMDict::stream(JMDICT_SAMPLE.as_bytes())
+        .collect::<Result<_, _>>()
+        .unwrap();
+
+    assert_eq!(entries.len(), 3);
+    assert_eq!(entries[0].seq, 1358280);
+    assert_eq!(entries[0].kanji[0].text, "食べる");
+    assert_eq!(
+        entries[0].sense[0].pos,
+        vec![PartOfSpeech::Ichidan, PartOfSpeech::Transitive]
+    );
+    assert_eq!(entries[0].sense[0].gloss[1].lang, Language::GERMAN);
+    assert_eq!(entries[2].reading[0].text, "コーヒー");
+    assert_eq!(entries[2].sense[0].source_lang[0].lang, Language::DUTCH);
+    assert_eq!(entries[2].sense[0].misc, vec![Misc::UsuallyKana]);
+}
+
+#[test]
+fn stream_keeps_character_references_in_entities() {
+    let xml = r#"<!DOCTYPE doc [
+<!ENTITY rd "R&#38;D &#x3C;lab&#x3E;">
+]>
+<doc><entry><info>&rd;</info></entry></doc>"#;
+    let mut reader = ElementReader::new(xml.as_bytes());
+    let entry = reader.next_element("entry").unwrap().unwrap();
+    let doc = roxmltree::Document::parse(&entry).unwrap();
+    let info = doc.descendants().find(|n| n.has_tag_name("info")).unwrap();
+    assert_eq!(info.text(), Some("R&D <lab>"));
+}
+
+#[test]
+fn entity_enums_round_trip() {
+    let pos = PartOfSpeech::from_code("v5k");
+    assert_eq!(pos, PartOfSpeech::GodanKu);
+    assert_eq!(pos.code(), "v5k");
+    assert_eq!(pos.description(), Some("Godan verb with `ku' ending"));
+    assert_eq!(
+        "Godan verb with `ku' ending".parse::<PartOfSpeech>(),
+        Ok(PartOfSpeech::GodanKu)
+    );
+
+    // Descriptions as the JMdict DTD declares them, expanded by the reader.
+    let xml = r#"<!DOCTYPE JMdict [
+<!ENTITY adj-ku "`ku' adjective (archaic)">
+<!ENTITY adj-no "nouns which may take the genitive case particle `no'">
+]>
+<JMdict><entry><pos>&adj-ku;</pos><pos>&adj-no;</pos></entry></JMdict>"#;
+    let mut reader = ElementReader::new(xml.as_bytes());
+    let entry = reader.next_element("entry").unwrap().unwrap();
+    let doc = roxmltree::Document::parse(&entry).unwrap();
+    let pos: Vec<_> = doc
+        .descendants()
+        .filter(|n| n.has_tag_name("pos"))
+        .map(|n| PartOfSpeech::from_description(n.text().unwrap()))
+        .collect();
+    assert_eq!(pos, vec![PartOfSpeech::AdjectiveKu, PartOfSpeech::NounNo]);
+
+    assert_eq!(Field::from_code("med"), Field::Medicine);
+    assert_eq!(Dialect::from_description("Kansai-ben"), Dialect::Kansai);
+
+    let unknown = Misc::from_code("new-tag");
+    assert_eq!(unknown, Misc::Unknown("new-tag".to_owned()));
+    assert_eq!(unknown.code(), "new-tag");
+    assert_eq!(unknown.description(), None);
+}
+
+const KANJIDIC_SAMPLE: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE kanjidic2 [
+<!ELEMENT kanjidic2 (header,character*)>
+]>
+<kanjidic2>
+<header>
+<file_version>4</file_version>
+<database_version>2020-152</database_version>
+<date_of_creation>2020-05-31</date_of_creation>
+</header>
+<character>
+<literal>日</literal>
+<codepoint>
+<cp_value cp_type="ucs">65e5</cp_value>
+<cp_value cp_type="jis208">1-38-92</cp_value>
+</codepoint>
+<radical>
+<rad_value rad_type="classical">72</rad_value>
+</radical>
+<misc>
+<grade>1</grade>
+<stroke_count>4</stroke_count>
+<freq>1</freq>
+<jlpt>4</jlpt>
+</misc>
+<dic_number>
+<dic_ref dr_type="heisig">12</dic_ref>
+</dic_number>
+<reading_meaning>
+<rmgroup>
+<reading r_type="ja_on">ニチ</reading>
+<reading r_type="ja_on">ジツ</reading>
+<reading r_type="ja_kun">ひ</reading>
+<reading r_type="ja_kun">-び</reading>
+<reading r_type="ja_kun">-か</reading>
+<meaning>day</meaning>
+<meaning>sun</meaning>
+<meaning m_lang="es">sol</meaning>
+</rmgroup>
+<nanori>あ</nanori>
+</reading_meaning>
+</character>
+<character>
+<literal>本</literal>
+<codepoint>
+<cp_value cp_type="ucs">672c</cp_value>
+</codepoint>
+<radical>
+<rad_value rad_type="classical">75</rad_value>
+</radical>
+<misc>
+<grade>1</grade>
+<stroke_count>5</stroke_count>
+</misc>
+<reading_meaning>
+<rmgroup>
+<reading r_type="ja_on">ホン</reading>
+<reading r_type="ja_kun">もと</reading>
+<meaning>book</meaning>
+<meaning m_lang="es">libro</meaning>
+</rmgroup>
+</reading_meaning>
+</character>
+</kanjidic2>
+"#;
+
+fn sample_kanjidic() -> Kanjidic {
+    let mut stream = Kanjidic::stream(KANJIDIC_SAMPLE.as_bytes()).unwrap();
+    let entries = stream.by_ref().collect::<Result<_, _>>().unwrap();
+    Kanjidic {
+        file_version: stream.file_version,
+        database_version: stream.database_version,
+        creation_date: stream.creation_date,
+        entries,
+    }
+}
+
+#[test]
+fn kanjidic_stream_works() {
+    let mut stream = Kanjidic::stream(KANJIDIC_SAMPLE.as_bytes()).unwrap();
+    assert_eq!(stream.file_version, 4);
+    assert_eq!(stream.database_version, "2020-152");
+    assert_eq!(stream.creation_date, "2020-05-31");
+
+    let literals: Vec<_> = stream.by_ref().map(|e| e.unwrap().literal).collect();
+    assert_eq!(literals, vec!["日", "本"]);
+}
+
+#[test]
+fn xref_parses_and_resolves() {
+    let xref = XRef::from("食べる・たべる・1");
+    assert_eq!(xref.kanji.as_deref(), Some("食べる"));
+    assert_eq!(xref.reading.as_deref(), Some("たべる"));
+    assert_eq!(xref.sense_index, Some(0));
+    assert_eq!(xref.to_string(), "食べる・たべる・1");
+
+    let xref = XRef::from("コーヒー");
+    assert_eq!(xref.kanji, None);
+    assert_eq!(xref.reading.as_deref(), Some("コーヒー"));
+    assert_eq!(xref.sense_index, None);
+
+    let xref = XRef::from("ジョン・スミス");
+    assert_eq!(xref.kanji, None);
+    assert_eq!(xref.reading.as_deref(), Some("ジョン・スミス"));
+    let xref = XRef::from("ジョン・スミス・2");
+    assert_eq!(xref.reading.as_deref(), Some("ジョン・スミス"));
+    assert_eq!(xref.sense_index, Some(1));
+
+    let dict = sample_jmdict();
+    let targets = dict.resolve_xref(&XRef::from("食べる・1"));
+    assert_eq!(targets.len(), 1);
+    assert_eq!(targets[0].0.seq, 1358280);
+    assert!(dict.resolve_xref(&XRef::from("食べる・2")).is_empty());
+}
+
+#[test]
+fn priority_tags_are_kept() {
+    let dict = sample_jmdict();
+    let taberu = dict.find_seq(1358280).unwrap();
+    assert_eq!(
+        taberu.kanji[0].pri_ref,
+        vec![PriRef::Ichi1, PriRef::News2, PriRef::NF(25)]
+    );
+    assert!(taberu.is_common());
+    assert_eq!(taberu.frequency_rank(), Some(25));
+
+    let coffee = dict.find_seq(1044840).unwrap();
+    assert!(!coffee.is_common());
+    assert_eq!(coffee.frequency_rank(), None);
+}
+
+#[test]
+fn kanji_and_reading_info_are_parsed() {
+    let dict = sample_jmdict();
+    let taberu = dict.find_seq(1358280).unwrap();
+    assert_eq!(taberu.kanji[1].info, vec![KanjiInfo::SearchOnlyKanji]);
+    assert!(taberu.kanji[1].is_search_only());
+    assert!(!taberu.kanji[0].is_search_only());
+    assert!(!taberu.reading[0].no_kanji);
+
+    let coffee = dict.find_seq(1044840).unwrap();
+    assert!(coffee.reading[1].no_kanji);
+    assert_eq!(coffee.reading[1].info, vec![ReadingInfo::IrregularKana]);
+    assert!(coffee.reading[1].is_irregular());
+}
+
+#[test]
+fn forms_respect_restrictions() {
+    let dict = sample_jmdict();
+    let kyou = dict.find_seq(1579470).unwrap();
+    let forms: Vec<_> = kyou
+        .forms()
+        .iter()
+        .map(|f| {
+            let glosses: Vec<_> = f
+                .senses
+                .iter()
+                .map(|s| s.gloss[0].content.as_deref().unwrap())
+                .collect();
+            (
+                f.kanji.unwrap().text.as_str(),
+                f.reading.text.as_str(),
+                glosses,
+            )
+        })
+        .collect();
+    assert_eq!(
+        forms,
+        vec![
+            ("今日", "きょう", vec!["today"]),
+            ("今日", "こんにち", vec!["these days"]),
+            ("今日は", "こんにち", vec!["hello"]),
+        ]
+    );
+
+    let coffee = dict.find_seq(1044840).unwrap();
+    let forms = coffee.forms();
+    assert_eq!(forms.len(), 2);
+    assert!(forms.iter().all(|f| f.kanji.is_none()));
+}
+
+#[test]
+fn pos_is_inherited_by_default() {
+    let dict = sample_jmdict();
+    let kyou = dict.find_seq(1579470).unwrap();
+    assert_eq!(kyou.sense[2].pos, vec![PartOfSpeech::Noun]);
+
+    let options = ParseOptions {
+        inherit_pos: false,
+        ..ParseOptions::default()
+    };
+    let entry = JMDict::stream_with_options(JMDICT_SAMPLE.as_bytes(), options)
+        .map(|e| e.unwrap())
+        .find(|e| e.seq == 1579470)
+        .unwrap();
+    assert!(entry.sense[2].pos.is_empty());
+}
+
+#[test]
+fn indexed_lookups_match_linear_search() {
+    let index = sample_jmdict().build_index();
+    let dict = index.dict();
+
+    assert_eq!(index.find_seq(1579470).unwrap().seq, 1579470);
+    assert!(index.find_seq(1).is_none());
+    for phrase in &["今日", "こんにち", "コーヒー", "食べる", "ない"] {
+        let indexed: Vec<_> = index.search(phrase).iter().map(|e| e.seq).collect();
+        let linear: Vec<_> = dict.search(phrase).iter().map(|e| e.seq).collect();
+        assert_eq!(indexed, linear);
+    }
+    assert_eq!(index.resolve_xref(&XRef::from("今日・きょう")).len(), 3);
+
+    let kanjidic = sample_kanjidic().build_index();
+    assert_eq!(kanjidic.find_literal("本").unwrap().literal, "本");
+    assert_eq!(
+        kanjidic.find_codepoint("ucs", "65E5").unwrap().literal,
+        "日"
+    );
+    assert!(kanjidic.find_codepoint("ucs", "0000").is_none());
+}
+
+#[test]
+fn kana_normalization() {
+    let options = NormalizeOptions::default();
+    assert_eq!(kana::normalize("コーヒー", &options), "こーひー");
+    assert_eq!(kana::normalize("ｺｰﾋｰ", &options), "こーひー");
+    assert_eq!(kana::to_full_width("ｶﾞｯﾂﾎﾟｰｽﾞ"), "ガッツポーズ");
+    assert_eq!(kana::to_katakana("ひらがな"), "ヒラガナ");
+    assert_eq!(kana::expand_long_vowels("コーヒー"), "コオヒイ");
+
+    let dict = sample_jmdict();
+    let found = dict.search_normalized("こーひー", &options);
+    assert_eq!(found.len(), 1);
+    assert_eq!(found[0].seq, 1044840);
+
+    let expand = NormalizeOptions {
+        expand_long_vowels: true,
+        ..NormalizeOptions::default()
+    };
+    assert_eq!(dict.search_normalized("こおひい", &expand).len(), 1);
+
+    let kanjidic = sample_kanjidic();
+    let literals: Vec<_> = kanjidic
+        .search_normalized("にち", &options)
+        .iter()
+        .map(|e| e.literal.as_str())
+        .collect();
+    assert_eq!(literals, vec!["日"]);
+    assert_eq!(kanjidic.search_normalized("ビ", &options).len(), 1);
+}
+
+#[test]
+fn romaji_to_kana() {
+    assert_eq!(romaji::to_hiragana("taberu"), "たべる");
+    assert_eq!(romaji::to_hiragana("shinbun"), "しんぶん");
+    assert_eq!(romaji::to_hiragana("shimbun"), "しんぶん");
+    assert_eq!(romaji::to_hiragana("sinbun"), "しんぶん");
+    assert_eq!(romaji::to_hiragana("kitte"), "きって");
+    assert_eq!(romaji::to_hiragana("matcha"), "まっちゃ");
+    assert_eq!(romaji::to_hiragana("kon'ya"), "こんや");
+    assert_eq!(romaji::to_hiragana("konya"), "こにゃ");
+    assert_eq!(romaji::to_hiragana("konnichiha"), "こんにちは");
+    assert_eq!(romaji::to_hiragana("honn"), "ほん");
+    assert_eq!(romaji::to_hiragana("kinnen"), "きんねん");
+    assert_eq!(romaji::to_hiragana("sannbonn"), "さんぼん");
+    assert_eq!(romaji::to_hiragana("tyotto"), "ちょっと");
+    assert_eq!(romaji::to_hiragana("hudi"), "ふぢ");
+    assert_eq!(romaji::to_hiragana("kyō"), "きょう");
+    assert_eq!(
+        romaji::to_hiragana_candidates("ōkī"),
+        vec!["おうきい", "おおきい"]
+    );
+    assert_eq!(romaji::to_hiragana("semmon"), "せんもん");
+    let many = "ō".repeat(20);
+    let candidates = romaji::to_hiragana_candidates(&many);
+    assert_eq!(candidates.len(), 1 << romaji::MAX_AMBIGUOUS_VOWELS);
+    assert_eq!(candidates[0], romaji::to_hiragana(&many));
+
+    let dict = sample_jmdict();
+    let seqs = |r: &str| -> Vec<u32> { dict.search_romaji(r).iter().map(|e| e.seq).collect() };
+    assert_eq!(seqs("taberu"), vec![1358280]);
+    assert_eq!(seqs("kyou"), vec![1579470]);
+    assert_eq!(seqs("konnichi"), vec![1579470]);
+    assert_eq!(seqs("koohii"), vec![1044840]);
+    assert_eq!(seqs("kōhī"), vec![1044840]);
+}
+
+#[test]
+fn kana_to_romaji() {
+    use crate::romaji::{LongVowels, RomanizeOptions, System};
+
+    let opts = |system, long_vowels| RomanizeOptions {
+        system,
+        long_vowels,
+        ..RomanizeOptions::default()
+    };
+    let hepburn = RomanizeOptions::default();
+    assert_eq!(romaji::romanize("とうきょう", &hepburn), "tōkyō");
+    assert_eq!(romaji::romanize("コーヒー", &hepburn), "kōhī");
+    assert_eq!(romaji::romanize("しんぶん", &hepburn), "shinbun");
+    assert_eq!(romaji::romanize("きんえん", &hepburn), "kin'en");
+    assert_eq!(romaji::romanize("まっちゃ", &hepburn), "matcha");
+    assert_eq!(romaji::romanize("ちぢむ", &hepburn), "chijimu");
+    assert_eq!(romaji::romanize("ファイル", &hepburn), "fairu");
+    assert_eq!(romaji::romanize("せんせい", &hepburn), "sensei");
+    assert_eq!(romaji::romanize("おもう", &hepburn), "omō");
+    let verbs = RomanizeOptions {
+        merge_ou: false,
+        ..RomanizeOptions::default()
+    };
+    assert_eq!(romaji::romanize("おもう", &verbs), "omou");
+    assert_eq!(romaji::romanize("おおい", &verbs), "ōi");
+
+    let doubled = opts(System::Hepburn, LongVowels::Doubled);
+    assert_eq!(romaji::romanize("コーヒー", &doubled), "koohii");
+    assert_eq!(romaji::romanize("とうきょう", &doubled), "toukyou");
+
+    let kunrei = opts(System::Kunrei, LongVowels::Circumflexes);
+    assert_eq!(romaji::romanize("しゃしん", &kunrei), "syasin");
+    assert_eq!(romaji::romanize("まっちゃ", &kunrei), "mattya");
+    assert_eq!(romaji::romanize("ちぢむ", &kunrei), "tizimu");
+    assert_eq!(romaji::romanize("とうきょう", &kunrei), "tôkyô");
+
+    let nihon = opts(System::Nihon, LongVowels::Macrons);
+    assert_eq!(romaji::romanize("ちぢむ", &nihon), "tidimu");
+    assert_eq!(romaji::romanize("ほんをよむ", &nihon), "honwoyomu");
+
+    let passport = opts(System::Passport, LongVowels::Macrons);
+    assert_eq!(romaji::romanize("おおの", &passport), "ono");
+    assert_eq!(romaji::romanize("なんば", &passport), "namba");
+    assert_eq!(romaji::romanize("しんいち", &passport), "shinichi");
+
+    let dict = sample_jmdict();
+    let reading = &dict.find_seq(1358280).unwrap().reading[0];
+    assert_eq!(reading.romanize(&hepburn), "taberu");
+
+    let kanjidic = sample_kanjidic();
+    let sun = &kanjidic.entries[0];
+    let readings: Vec<_> = sun.reading_meanings[0]
+        .readings
+        .iter()
+        .filter_map(|r| r.romanize(&hepburn))
+        .collect();
+    assert_eq!(readings, vec!["nichi", "jitsu", "hi", "-bi", "-ka"]);
+    assert_eq!(sun.romanized_nanori(&hepburn), vec!["a"]);
+}
+
+#[test]
+fn deinflection_chains() {
+    use crate::deinflect::{self, Reason, WordClass};
+
+    let find = |word: &str, term: &str| {
+        deinflect::deinflect(word)
+            .into_iter()
+            .find(|d| d.term == term)
+            .map(|d| (d.reasons, d.class))
+    };
+
+    assert_eq!(
+        find("食べなかった", "食べる"),
+        Some((
+            vec![Reason::Negative, Reason::Past],
+            Some(WordClass::Ichidan)
+        ))
+    );
+    assert_eq!(
+        find("高くて", "高い"),
+        Some((vec![Reason::Te], Some(WordClass::IAdjective)))
+    );
+    assert_eq!(
+        find("書いていた", "書く"),
+        Some((
+            vec![Reason::Te, Reason::Progressive, Reason::Past],
+            Some(WordClass::Godan)
+        ))
+    );
+    assert_eq!(
+        find("こなかった", "くる"),
+        Some((vec![Reason::Negative, Reason::Past], Some(WordClass::Kuru)))
+    );
+    assert_eq!(
+        find("勉強しませんでした", "勉強する"),
+        Some((
+            vec![Reason::Polite, Reason::Negative, Reason::Past],
+            Some(WordClass::Suru)
+        ))
+    );
+    // Intermediate forms are never offered as dictionary forms.
+    assert_eq!(find("食べていた", "食べて"), None);
+
+    let dict = sample_jmdict();
+    let hits = dict.search_deinflected("食べさせられた");
+    assert_eq!(hits.len(), 1);
+    assert_eq!(hits[0].0.seq, 1358280);
+    assert_eq!(
+        hits[0].1.reasons,
+        vec![Reason::Causative, Reason::PotentialOrPassive, Reason::Past]
+    );
+    // コーヒー is a noun, so it cannot be the stem of a verb form.
+    assert!(dict.search_deinflected("コーヒーた").is_empty());
+
+    let indexed = sample_jmdict().build_index();
+    let seqs = |word| -> Vec<u32> {
+        indexed
+            .search_deinflected(word)
+            .iter()
+            .map(|(e, _)| e.seq)
+            .collect()
+    };
+    assert_eq!(seqs("たべました"), vec![1358280]);
+    assert_eq!(seqs("今日"), vec![1579470]);
+}
+
+#[test]
+fn conjugation_tables() {
+    use crate::conjugate::{self, Conjugation};
+
+    let form = |word: &str, pos: PartOfSpeech, c: Conjugation| {
+        conjugate::conjugate(word, &pos)
+            .unwrap()
+            .into_iter()
+            .find(|(conjugation, _)| *conjugation == c)
+            .map(|(_, text)| text)
+            .unwrap()
+    };
+
+    assert_eq!(
+        form("書く", PartOfSpeech::GodanKu, Conjugation::Te),
+        "書いて"
+    );
+    assert_eq!(
+        form("行く", PartOfSpeech::GodanIku, Conjugation::Past),
+        "行った"
+    );
+    assert_eq!(
+        form("問う", PartOfSpeech::GodanUSpecial, Conjugation::Te),
+        "問うて"
+    );
+    assert_eq!(
+        form("買う", PartOfSpeech::GodanU, Conjugation::Negative),
+        "買わない"
+    );
+    assert_eq!(
+        form(
+            "ある",
+            PartOfSpeech::GodanRuIrregular,
+            Conjugation::Negative
+        ),
+        "ない"
+    );
+    assert_eq!(
+        form("なさる", PartOfSpeech::GodanAru, Conjugation::NonPastPolite),
+        "なさいます"
+    );
+    assert_eq!(
+        form("勉強", PartOfSpeech::Suru, Conjugation::Potential),
+        "勉強できる"
+    );
+    assert_eq!(
+        form("する", PartOfSpeech::SuruIncluded, Conjugation::Volitional),
+        "しよう"
+    );
+    let ai_suru = |c| form("愛する", PartOfSpeech::SuruSpecial, c);
+    assert_eq!(ai_suru(Conjugation::Negative), "愛さない");
+    assert_eq!(ai_suru(Conjugation::Potential), "愛せる");
+    assert_eq!(ai_suru(Conjugation::Passive), "愛される");
+    assert_eq!(ai_suru(Conjugation::Imperative), "愛せ");
+    assert_eq!(ai_suru(Conjugation::Te), "愛して");
+    assert_eq!(ai_suru(Conjugation::Past), "愛した");
+    assert!(conjugate::conjugate("愛", &PartOfSpeech::SuruSpecial).is_none());
+    assert_eq!(
+        form("来る", PartOfSpeech::Kuru, Conjugation::Negative),
+        "来ない"
+    );
+    assert_eq!(
+        form("くる", PartOfSpeech::Kuru, Conjugation::Negative),
+        "こない"
+    );
+    assert_eq!(
+        form("くる", PartOfSpeech::Kuru, Conjugation::Conditional),
+        "くれば"
+    );
+    assert_eq!(
+        form("いい", PartOfSpeech::AdjectiveYoiIi, Conjugation::Past),
+        "よかった"
+    );
+    assert_eq!(
+        form("高い", PartOfSpeech::Adjective, Conjugation::Te),
+        "高くて"
+    );
+    assert!(conjugate::conjugate("今日", &PartOfSpeech::Noun).is_none());
+
+    let dict = sample_jmdict();
+    let tables = dict.find_seq(1358280).unwrap().conjugations();
+    // 喰べる is a search-only form but is still conjugated.
+    assert_eq!(tables.len(), 2);
+    assert_eq!(tables[0].pos, PartOfSpeech::Ichidan);
+    let past = tables[0]
+        .forms
+        .iter()
+        .find(|f| f.conjugation == Conjugation::NegativePast)
+        .unwrap();
+    assert_eq!(past.written, "食べなかった");
+    assert_eq!(past.reading, "たべなかった");
+    assert!(dict.find_seq(1044840).unwrap().conjugations().is_empty());
+}
+
+#[test]
+fn pattern_search() {
+    let dict = sample_jmdict().build_index();
+    let seqs = |entries: Vec<&crate::jmdict::Entry>| -> Vec<u32> {
+        entries.iter().map(|e| e.seq).collect()
+    };
+
+    assert_eq!(seqs(dict.search_prefix("今日")), vec![1579470]);
+    assert_eq!(seqs(dict.search_prefix("コー")), vec![1044840]);
+    assert_eq!(seqs(dict.search_suffix("べる")), vec![1358280]);
+    assert_eq!(seqs(dict.search_pattern("食べ*")), vec![1358280]);
+    assert_eq!(seqs(dict.search_pattern("*は")), vec![1579470]);
+    assert_eq!(seqs(dict.search_pattern("?べる")), vec![1358280]);
+    assert_eq!(seqs(dict.search_pattern("??")), vec![1579470]);
+    assert_eq!(seqs(dict.search_pattern("コー*ー")), vec![1044840]);
+    assert_eq!(seqs(dict.search_pattern("こんにち")), vec![1579470]);
+    assert!(dict.search_pattern("?ける").is_empty());
+    assert_eq!(seqs(dict.search_pattern("*ー*ー")), vec![1044840]);
+    assert!(dict.search_pattern("*ー*ー*ー").is_empty());
+
+    let regex = |pattern| seqs(dict.search_regex(pattern).unwrap());
+    assert_eq!(regex("^(きょう|たべる)$"), vec![1358280, 1579470]);
+    assert_eq!(regex("^コー(ヒー|ヒ)$"), vec![1044840]);
+    assert_eq!(regex("日は?$"), vec![1579470]);
+    assert_eq!(regex("^(?x) コー ヒー $"), vec![1044840]);
+    assert!(dict.search_regex("^コー[").is_err());
+}
+
+#[test]
+fn gloss_search_is_ranked() {
+    let xml = r#"<JMdict>
+<entry><ent_seq>100</ent_seq><r_ele><reb>あさごはんをたべる</reb></r_ele>
+<sense><gloss>to eat breakfast</gloss></sense></entry>
+<entry><ent_seq>200</ent_seq><r_ele><reb>のむ</reb></r_ele>
+<sense><gloss>to drink</gloss></sense><sense><gloss>to eat</gloss></sense></entry>
+<entry><ent_seq>300</ent_seq><r_ele><reb>たべる</reb><re_pri>ichi1</re_pri></r_ele>
+<sense><gloss>to eat</gloss><gloss xml:lang="ger">essen</gloss></sense></entry>
+<entry><ent_seq>400</ent_seq><r_ele><reb>くう</reb></r_ele>
+<sense><gloss>To Eat</gloss></sense></entry>
+</JMdict>"#;
+    let entries = JMDict::stream(xml.as_bytes())
+        .collect::<Result<_, _>>()
+        .unwrap();
+    let dict = JMDict { entries }.build_index();
+
+    let hits = dict.search_gloss("to eat");
+    let ranked: Vec<_> = hits
+        .iter()
+        .map(|h| (h.entry.seq, h.sense, h.gloss))
+        .collect();
+    assert_eq!(
+        ranked,
+        vec![(300, 0, 0), (400, 0, 0), (200, 1, 0), (100, 0, 0)]
+    );
+    assert_eq!(hits[1].gloss().content.as_deref(), Some("To Eat"));
+    assert!(hits.windows(2).all(|w| w[0].score >= w[1].score));
+
+    assert_eq!(dict.search_gloss("EAT").len(), 4);
+    assert_eq!(dict.search_gloss("breakfast eat").len(), 1);
+    // Only English glosses are indexed.
+    assert!(dict.search_gloss("essen").is_empty());
+    assert!(dict.search_gloss("").is_empty());
+}
+
+#[test]
+fn fuzzy_search_and_suggestions() {
+    use crate::fuzzy;
+
+    assert_eq!(fuzzy::distance("きょう", "きょう"), 0.0);
+    assert_eq!(fuzzy::distance("きょう", "キョウ"), 0.0);
+    assert_eq!(fuzzy::distance("きょう", "きよう"), fuzzy::CHEAP_EDIT);
+    assert_eq!(fuzzy::distance("たべる", "だべる"), fuzzy::CHEAP_EDIT);
+    assert_eq!(fuzzy::distance("たべる", "たべ"), 1.0);
+    assert_eq!(fuzzy::distance("coffee", "Cofee"), 1.0);
+    assert_eq!(fuzzy::distance_within("たべる", "たべ", 1.0), Some(1.0));
+    assert_eq!(fuzzy::distance_within("たべる", "のみもの", 1.0), None);
+
+    let dict = sample_jmdict().build_index();
+    let hits = dict.search_fuzzy("きよう", 1.0);
+    assert_eq!(hits[0].entry.seq, 1579470);
+    assert_eq!(hits[0].text, "きょう");
+    assert_eq!(hits[0].distance, fuzzy::CHEAP_EDIT);
+
+    let hits = dict.search_fuzzy("cofee", 1.0);
+    assert_eq!(hits.len(), 1);
+    assert_eq!((hits[0].entry.seq, hits[0].text), (1044840, "coffee"));
+    assert!(dict.search_fuzzy("cofee", 0.5).is_empty());
+
+    assert_eq!(dict.suggest("たぺる", 3), vec!["たべる"]);
+    assert_eq!(dict.suggest("cofee", 3), vec!["coffee"]);
+    assert_eq!(dict.suggest("thse dayz", 3), vec!["these days"]);
+    assert!(dict.suggest("coffee", 3).is_empty());
+}
+
+#[test]
+fn scan_finds_longest_matches() {
+    use crate::deinflect::Reason;
+
+    let dict = sample_jmdict().build_index();
+
+    let text = "今日は天気";
+    let matches = dict.scan(text, 0);
+    let found: Vec<_> = matches.iter().map(|m| &text[..m.len]).collect();
+    // Both forms belong to the same entry, so only the longest is kept.
+    assert_eq!(found, vec!["今日は"]);
+    assert_eq!(matches[0].entry.seq, 1579470);
+
+    let text = "私は食べなかった。";
+    let offset = text.find('食').unwrap();
+    let matches = dict.scan(text, offset);
+    assert_eq!(matches.len(), 1);
+    assert_eq!(&text[offset..offset + matches[0].len], "食べなかった");
+    assert_eq!(matches[0].entry.seq, 1358280);
+    assert_eq!(matches[0].deinflection.term, "食べる");
+    assert_eq!(
+        matches[0].deinflection.reasons,
+        vec![Reason::Negative, Reason::Past]
+    );
+
+    let matches = dict.scan("コーヒーをたべる", 0);
+    assert_eq!(matches[0].len, "コーヒー".len());
+    assert!(matches[0].deinflection.reasons.is_empty());
+
+    assert!(dict.scan(text, 0).is_empty());
+    assert!(dict.scan(text, 1).is_empty());
+}
+
+#[test]
+fn tokenizer_picks_cheapest_path() {
+    let xml = r#"<JMdict>
+<entry><ent_seq>1</ent_seq><k_ele><keb>私</keb><ke_pri>ichi1</ke_pri></k_ele>
+<r_ele><reb>わたし</reb><re_pri>ichi1</re_pri></r_ele><sense><pos>pn</pos><gloss>I</gloss></sense></entry>
+<entry><ent_seq>2</ent_seq><r_ele><reb>は</reb><re_pri>spec1</re_pri></r_ele>
+<sense><pos>prt</pos><gloss>topic marker</gloss></sense></entry>
+<entry><ent_seq>3</ent_seq><k_ele><keb>歯</keb><ke_pri>ichi1</ke_pri></k_ele>
+<r_ele><reb>は</reb><re_pri>ichi1</re_pri></r_ele><sense><pos>n</pos><gloss>tooth</gloss></sense></entry>
+<entry><ent_seq>4</ent_seq><r_ele><reb>を</reb><re_pri>spec1</re_pri></r_ele>
+<sense><pos>prt</pos><gloss>object marker</gloss></sense></entry>
+<entry><ent_seq>5</ent_seq><r_ele><reb>た</reb></r_ele>
+<sense><pos>n</pos><gloss>rice field</gloss></sense></entry>
+<entry><ent_seq>6</ent_seq><k_ele><keb>話す</keb><ke_pri>ichi1</ke_pri></k_ele>
+<r_ele><reb>はなす</reb><re_pri>ichi1</re_pri></r_ele><sense><pos>v5s</pos><gloss>to speak</gloss></sense></entry>
+</JMdict>"#;
+    let mut entries: Vec<_> = JMDict::stream(xml.as_bytes())
+        .collect::<Result<_, _>>()
+        .unwrap();
+    entries.extend(sample_jmdict().entries);
+    let dict = JMDict { entries }.build_index();
+
+    let text = "私はコーヒーを食べなかった。";
+    let tokens = dict.tokenize(text);
+    let found: Vec<_> = tokens.iter().map(|t| (t.surface.as_str(), t.seq)).collect();
+    assert_eq!(
+        found,
+        vec![
+            ("私", Some(1)),
+            ("は", Some(2)),
+            ("コーヒー", Some(1044840)),
+            ("を", Some(4)),
+            ("食べなかった", Some(1358280)),
+            ("。", None),
+        ]
+    );
+    assert!(tokens.iter().all(|t| text[t.span.clone()] == t.surface));
+
+    // 今日 is a shorter form of the same entry as 今日は; the path through
+    // it is cheaper here.
+    let tokens = dict.tokenize("今日はなす");
+    let found: Vec<_> = tokens.iter().map(|t| (t.surface.as_str(), t.seq)).collect();
+    assert_eq!(found, vec![("今日", Some(1579470)), ("はなす", Some(6))]);
+
+    let tokens = dict.tokenize("ラーメン 2杯");
+    let found: Vec<_> = tokens.iter().map(|t| (t.surface.as_str(), t.seq)).collect();
+    assert_eq!(found, vec![("ラーメン", None), ("2", None), ("杯", None)]);
+}
+
+#[test]
+fn furigana_alignment() {
+    use crate::furigana::Segment;
+
+    let character = |literal: &str, readings: &str| {
+        format!(
+            "<character><literal>{}</literal><codepoint></codepoint><radical>\
+             <rad_value rad_type=\"classical\">1</rad_value></radical><misc>\
+             <stroke_count>1</stroke_count></misc><reading_meaning><rmgroup>{}\
+             </rmgroup></reading_meaning></character>",
+            literal, readings
+        )
+    };
+    let xml = format!(
+        "<kanjidic2><header><file_version>4</file_version>\
+         <database_version>2020-152</database_version>\
+         <date_of_creation>2020-05-31</date_of_creation></header>{}{}{}{}</kanjidic2>",
+        character(
+            "取",
+            r#"<reading r_type="ja_on">シュ</reading><reading r_type="ja_kun">と.る</reading>"#
+        ),
+        character(
+            "引",
+            r#"<reading r_type="ja_on">イン</reading><reading r_type="ja_kun">ひ.く</reading>"#
+        ),
+        character(
+            "今",
+            r#"<reading r_type="ja_on">コン</reading><reading r_type="ja_kun">いま</reading>"#
+        ),
+        character(
+            "人",
+            r#"<reading r_type="ja_on">ジン</reading><reading r_type="ja_kun">ひと</reading>"#
+        ),
+    );
+    let mut kanjidic = sample_kanjidic();
+    kanjidic.entries.extend(
+        Kanjidic::stream(xml.as_bytes())
+            .unwrap()
+            .map(Result::unwrap),
+    );
+    let kanjidic = kanjidic.build_index();
+
+    let ruby = |written: &str, reading: &str| -> Vec<(String, Option<String>)> {
+        kanjidic
+            .furigana(written, reading)
+            .into_iter()
+            .map(|Segment { text, ruby }| (text, ruby))
+            .collect()
+    };
+    let seg = |text: &str, ruby: Option<&str>| (text.to_owned(), ruby.map(str::to_owned));
+
+    assert_eq!(
+        ruby("取り引き", "とりひき"),
+        vec![
+            seg("取", Some("と")),
+            seg("り", None),
+            seg("引", Some("ひ")),
+            seg("き", None)
+        ]
+    );
+    // Gemination and rendaku.
+    assert_eq!(
+        ruby("日本", "にっぽん"),
+        vec![seg("日", Some("にっ")), seg("本", Some("ぽん"))]
+    );
+    assert_eq!(
+        ruby("人々", "ひとびと"),
+        vec![seg("人", Some("ひと")), seg("々", Some("びと"))]
+    );
+    // Jukujikun keep the reading as a whole.
+    assert_eq!(ruby("今日", "きょう"), vec![seg("今日", Some("きょう"))]);
+    assert_eq!(
+        ruby("今日は", "こんにちは"),
+        vec![
+            seg("今", Some("こん")),
+            seg("日", Some("にち")),
+            seg("は", None)
+        ]
+    );
+    assert_eq!(ruby("コーヒー", "コーヒー"), vec![seg("コーヒー", None)]);
+    assert_eq!(
+        ruby("今日", "コーヒー"),
+        vec![seg("今日", Some("コーヒー"))]
+    );
+}
+
+#[test]
+fn languages_convert_between_code_sets() {
+    use crate::errors::ParseError;
+    use crate::tatoeba::Tatoeba;
+
+    let german = Language::from_iso639_2("ger").unwrap();
+    assert_eq!(german, Language::GERMAN);
+    assert_eq!(Language::from_iso639_2("deu").unwrap(), german);
+    assert_eq!(Language::from_iso639_1("de").unwrap(), german);
+    assert_eq!(Language::from_iso639_3("deu").unwrap(), german);
+    assert_eq!(german.iso639_1(), Some("de"));
+    assert_eq!(german.iso639_2(), "ger");
+    assert_eq!(german.iso639_3(), "deu");
+    assert_eq!(Language::SPANISH.iso639_2(), "spa");
+
+    let mandarin = Language::from_iso639_3("cmn").unwrap();
+    assert_eq!(mandarin.iso639_1(), None);
+    assert_eq!(mandarin.to_string(), "cmn");
+
+    assert!(matches!(
+        Language::from_iso639_2(""),
+        Err(ParseError::ParseLanguage6392(code)) if code.is_empty()
+    ));
+    assert!(matches!(
+        Language::from_iso639_1("xx"),
+        Err(ParseError::ParseLanguage6391(code)) if code == "xx"
+    ));
+    assert!(matches!(
+        Language::from_iso639_3("EN"),
+        Err(ParseError::ParseLanguage6393(_))
+    ));
+
+    let dict = sample_jmdict();
+    let german_entries = dict.filter_language(Language::GERMAN);
+    assert_eq!(german_entries.len(), 1);
+    let senses = german_entries[0].senses_in(Language::GERMAN);
+    let glosses = senses[0].glosses_in(Language::GERMAN);
+    assert_eq!(glosses[0].content.as_deref(), Some("essen"));
+
+    let kanjidic = sample_kanjidic();
+    let spanish: Vec<_> = kanjidic
+        .filter_language(Language::SPANISH)
+        .iter()
+        .flat_map(|e| e.meanings_in(Language::SPANISH))
+        .map(|m| m.content.as_str())
+        .collect();
+    assert_eq!(spanish, vec!["sol", "libro"]);
+
+    let tsv = "1\tjpn\t今日は。\n2\teng\tHello.\n3\t\\N\t???\n";
+    let tatoeba = Tatoeba::from_reader(tsv.as_bytes(), None::<fn(&str) -> bool>).unwrap();
+    assert_eq!(tatoeba.filter_language(Language::JAPANESE).len(), 1);
+    assert_eq!(tatoeba.entries[2].language, None);
+    assert!(matches!(
+        Tatoeba::from_reader("1\tJapanese\tx".as_bytes(), None::<fn(&str) -> bool>),
+        Err(ParseError::ParseLanguage6393(_))
+    ));
+}
+
+const JMNEDICT_SAMPLE: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE JMnedict [
+<!ENTITY surname "family or surname">
+<!ENTITY place "place name">
+<!ENTITY person "full name of a particular person">
+]>
+<JMnedict>
+<entry>
+<ent_seq>5000001</ent_seq>
+<k_ele><keb>小泉</keb></k_ele>
+<r_ele><reb>こいずみ</reb></r_ele>
+<trans>
+<name_type>&surname;</name_type>
+<name_type>&place;</name_type>
+<trans_det>Koizumi</trans_det>
+</trans>
+</entry>
+<entry>
+<ent_seq>5000002</ent_seq>
+<k_ele><keb>夏目漱石</keb></k_ele>
+<r_ele><reb>なつめそうせき</reb></r_ele>
+<trans>
+<name_type>&person;</name_type>
+<xref>夏目</xref>
+<trans_det>Natsume Souseki (1867.2.9-1916.12.9)</trans_det>
+<trans_det xml:lang="ger">Natsume Sōseki</trans_det>
+</trans>
+</entry>
+</JMnedict>
+"#;
+
+#[test]
+fn jmnedict_stream_works() {
+    use crate::entities::NameType;
+    use crate::jmnedict::JMnedict;
+
+    let entries = JMnedict::stream(JMNEDICT_SAMPLE.as_bytes())
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+    let names = JMnedict { entries };
+
+    let koizumi = &names.search("こいずみ")[0];
+    assert_eq!(koizumi.seq, 5000001);
+    assert_eq!(
+        koizumi.translations[0].name_types,
+        vec![NameType::Surname, NameType::Place]
+    );
+    assert_eq!(koizumi.translations[0].details[0].lang, Language::ENGLISH);
+
+    let soseki = names.find_seq(5000002).unwrap();
+    let translation = &soseki.translations[0];
+    assert_eq!(translation.cross_refs[0].kanji.as_deref(), Some("夏目"));
+    assert_eq!(translation.details[1].lang, Language::GERMAN);
+    assert_eq!(translation.details[1].content, "Natsume Sōseki");
+
+    assert_eq!(names.filter_name_type(&NameType::Place).len(), 1);
+    assert_eq!(names.filter_name_type(&NameType::Person)[0].seq, 5000002);
+    assert_eq!(names.search_translation("KOIZUMI")[0].seq, 5000001);
+    assert_eq!(names.filter_kanji(|k| k.text.starts_with('夏')).len(), 1);
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn json_lines_round_trip() {
+    use crate::jmdict::Entry;
+    use crate::json;
+
+    let dict = sample_jmdict();
+    let mut out = Vec::new();
+    dict.write_json_lines(&mut out).unwrap();
+    let text = String::from_utf8(out).unwrap();
+    assert_eq!(text.lines().count(), dict.entries.len());
+
+    let first: serde_json::Value = serde_json::from_str(text.lines().next().unwrap()).unwrap();
+    assert_eq!(first["seq"], 1358280);
+    assert_eq!(first["kanji"][0]["pri_ref"][0], "ichi1");
+    assert_eq!(first["sense"][0]["pos"][0], "v1");
+    assert_eq!(first["sense"][0]["gloss"][0]["lang"], "eng");
+    assert!(first["sense"][0]["gloss"][0]["type"].is_null());
+
+    let entries: Vec<Entry> = json::read_lines(text.as_bytes())
+        .collect::<Result<_, _>>()
+        .unwrap();
+    assert_eq!(
+        entries[0].reading[0].pri_ref,
+        dict.entries[0].reading[0].pri_ref
+    );
+    assert_eq!(entries[0].sense[0].pos, dict.entries[0].sense[0].pos);
+    assert_eq!(entries[2].kanji.len(), dict.entries[2].kanji.len());
+
+    let kanjidic = sample_kanjidic();
+    let json = serde_json::to_value(&kanjidic).unwrap();
+    assert_eq!(json["file_version"], kanjidic.file_version);
+    assert_eq!(
+        json["entries"][0]["reading_meanings"][0]["readings"][0]["type"],
+        serde_json::json!({ "ja_on": [false, "none"] })
+    );
+}
+
+#[test]
+fn binary_cache_round_trip() {
+    use crate::cache::{self, Source};
+    use crate::errors::CacheError;
+    use std::fs;
+
+    let dir = env::temp_dir().join(format!("jmdict-cache-test-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let source = Source {
+        len: 1234,
+        modified: 1_600_000_000,
+        version: Some("2020-06-01".to_owned()),
+    };
+
+    let dict = sample_jmdict();
+    let path = dir.join("jmdict.bin");
+    dict.save_cache(&path, &source).unwrap();
+    assert_eq!(cache::read_source(&path).unwrap(), source);
+
+    let cached = JMDict::load_cache(&path, &source).unwrap();
+    assert_eq!(cached.entries.len(), dict.entries.len());
+    let (entry, original) = (&cached.entries[0], &dict.entries[0]);
+    assert_eq!(entry.seq, original.seq);
+    assert_eq!(entry.kanji[0].text, original.kanji[0].text);
+    assert_eq!(entry.reading[0].pri_ref, original.reading[0].pri_ref);
+    assert_eq!(entry.sense[0].pos, original.sense[0].pos);
+    assert_eq!(
+        entry.sense[0].gloss[0].content,
+        original.sense[0].gloss[0].content
+    );
+    assert_eq!(
+        cached.filter_language(Language::GERMAN).len(),
+        dict.filter_language(Language::GERMAN).len()
+    );
+
+    let changed = Source {
+        modified: source.modified + 1,
+        ..source.clone()
+    };
+    assert!(matches!(
+        JMDict::load_cache(&path, &changed),
+        Err(CacheError::SourceChanged)
+    ));
+    let changed = Source {
+        version: Some("2020-06-02".to_owned()),
+        ..source.clone()
+    };
+    assert!(matches!(
+        JMDict::load_cache(&path, &changed),
+        Err(CacheError::SourceChanged)
+    ));
+    assert!(matches!(
+        Kanjidic::load_cache(&path, &source),
+        Err(CacheError::InvalidHeader)
+    ));
+
+    let mut bytes = fs::read(&path).unwrap();
+    bytes[4] = 99;
+    fs::write(&path, &bytes).unwrap();
+    assert!(matches!(
+        JMDict::load_cache(&path, &source),
+        Err(CacheError::FormatVersion(99))
+    ));
+    bytes[4] = cache::FORMAT_VERSION as u8;
+    bytes.truncate(bytes.len() - 3);
+    fs::write(&path, &bytes).unwrap();
+    assert!(matches!(
+        JMDict::load_cache(&path, &source),
+        Err(CacheError::Corrupt)
+    ));
+
+    let kanjidic = sample_kanjidic();
+    let path = dir.join("kanjidic.bin");
+    kanjidic.save_cache(&path, &source).unwrap();
+    let cached = Kanjidic::load_cache(&path, &source).unwrap();
+    assert_eq!(cached.database_version, kanjidic.database_version);
+    assert_eq!(cached.entries[1].literal, "本");
+    assert_eq!(
+        cached.entries[0].reading_meanings[0].readings.len(),
+        kanjidic.entries[0].reading_meanings[0].readings.len()
+    );
+
+    let tsv = "1\tjpn\t今日は。\n2\t\\N\t???\n";
+    let tatoeba = Tatoeba::from_reader(tsv.as_bytes(), None::<fn(&str) -> bool>).unwrap();
+    let path = dir.join("tatoeba.bin");
+    tatoeba.save_cache(&path, &source, None).unwrap();
+    let cached = Tatoeba::load_cache(&path, &source, None).unwrap();
+    assert_eq!(cached.entries[0].content, "今日は。");
+    assert_eq!(cached.entries[1].language, None);
+
+    // The source version is read from the head of the dictionary files.
+    let xml = dir.join("JMdict.xml");
+    fs::write(&xml, JMDICT_SAMPLE).unwrap();
+    let path = dir.join("jmdict-from-file.bin");
+    let dict = JMDict::from_file_cached(xml.to_str().unwrap(), &path).unwrap();
+    let source = cache::read_source(&path).unwrap();
+    assert_eq!(source, Source::of(&xml).unwrap());
+    assert_eq!(source.version.as_deref(), Some("2020-06-01"));
+    let cached = JMDict::from_file_cached(xml.to_str().unwrap(), &path).unwrap();
+    assert_eq!(cached.entries.len(), dict.entries.len());
+
+    let xml = dir.join("kanjidic2.xml");
+    fs::write(&xml, KANJIDIC_SAMPLE).unwrap();
+    assert_eq!(
+        Source::of(&xml).unwrap().version.as_deref(),
+        Some("2020-152")
+    );
+
+    // A Tatoeba cache is only used for the languages it was filtered to.
+    let tsv_path = dir.join("sentences.csv");
+    fs::write(&tsv_path, tsv).unwrap();
+    let tsv_source = Source::of(&tsv_path).unwrap();
+    assert_eq!(tsv_source.version, None);
+    let path = dir.join("tatoeba-filtered.bin");
+    let tsv_file = tsv_path.to_str().unwrap();
+    let japanese = [Language::JAPANESE];
+    let tatoeba = Tatoeba::from_file_cached(tsv_file, &path, Some(&japanese)).unwrap();
+    assert_eq!(tatoeba.entries.len(), 1);
+    let cached = Tatoeba::load_cache(&path, &tsv_source, Some(&japanese)).unwrap();
+    assert_eq!(cached.entries.len(), 1);
+    let both = [Language::ENGLISH, Language::JAPANESE, Language::ENGLISH];
+    assert!(matches!(
+        Tatoeba::load_cache(&path, &tsv_source, None),
+        Err(CacheError::LanguagesChanged)
+    ));
+    assert!(matches!(
+        Tatoeba::load_cache(&path, &tsv_source, Some(&both)),
+        Err(CacheError::LanguagesChanged)
+    ));
+    let tatoeba = Tatoeba::from_file_cached(tsv_file, &path, None).unwrap();
+    assert_eq!(tatoeba.entries.len(), 2);
+    let tatoeba = Tatoeba::from_file_cached(tsv_file, &path, Some(&both)).unwrap();
+    assert_eq!(tatoeba.entries.len(), 1);
+    let reordered = [Language::JAPANESE, Language::ENGLISH];
+    let cached = Tatoeba::load_cache(&path, &tsv_source, Some(&reordered)).unwrap();
+    assert_eq!(cached.entries.len(), 1);
+
+    let leftovers = fs::read_dir(&dir)
+        .unwrap()
+        .filter(|e| e.as_ref().unwrap().path().extension() == Some("tmp".as_ref()))
+        .count();
+    assert_eq!(leftovers, 0);
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn mapped_views_match_parsed_dictionary() {
+    use crate::cache::Source;
+    use crate::errors::CacheError;
+    use crate::mapped::MappedJMDict;
+    use std::fs;
+
+    let dict = sample_jmdict();
+    let source = Source {
+        len: JMDICT_SAMPLE.len() as u64,
+        modified: 1_600_000_000,
+        version: Some("2020-06-01".to_owned()),
+    };
+    let path = env::temp_dir().join(format!("jmdict-mapped-test-{}.bin", std::process::id()));
+    dict.save_mapped(&path, &source).unwrap();
+
+    let mapped = MappedJMDict::open(&path, &source).unwrap();
+    assert_eq!(mapped.len(), 3);
+
+    let taberu = mapped.find_seq(1358280).unwrap();
+    let kanji: Vec<_> = taberu.kanji().map(|k| k.text()).collect();
+    assert_eq!(kanji, vec!["食べる", "喰べる"]);
+    let reading = taberu.reading().next().unwrap();
+    assert_eq!(
+        reading.pri_ref().collect::<Vec<_>>(),
+        vec![PriRef::Ichi1, PriRef::News2, PriRef::NF(25)]
+    );
+    assert!(taberu.is_common());
+    assert_eq!(taberu.frequency_rank(), Some(25));
+    let sense = taberu.sense().next().unwrap();
+    assert_eq!(
+        sense.pos().collect::<Vec<_>>(),
+        vec![PartOfSpeech::Ichidan, PartOfSpeech::Transitive]
+    );
+    let german = sense.glosses_in(Language::GERMAN);
+    assert_eq!(german[0].content(), Some("essen"));
+    assert_eq!(german[0].typ(), None);
+
+    let coffee = mapped.search_normalized("こーひー", &NormalizeOptions::default());
+    assert_eq!(coffee[0].seq(), 1044840);
+    let lsource = coffee[0]
+        .sense()
+        .next()
+        .unwrap()
+        .source_lang()
+        .next()
+        .unwrap();
+    assert_eq!(lsource.content(), Some("koffie"));
+    assert_eq!(lsource.lang(), Language::DUTCH);
+    assert!(lsource.full() && !lsource.wasei());
+
+    let seqs = |entries: Vec<crate::mapped::Entry>| -> Vec<u32> {
+        entries.iter().map(|e| e.seq()).collect()
+    };
+    assert_eq!(seqs(mapped.search("こんにち")), vec![1579470]);
+    assert_eq!(seqs(mapped.search_romaji("kyou")), vec![1579470]);
+    assert_eq!(
+        seqs(mapped.filter_language(Language::GERMAN)),
+        vec![1358280]
+    );
+    assert_eq!(
+        seqs(mapped.filter_gloss(|g| g.content() == Some("hello"))),
+        vec![1579470]
+    );
+    let deinflected = mapped.search_deinflected("食べなかった");
+    assert_eq!(deinflected[0].0.seq(), 1358280);
+    assert_eq!(
+        deinflected[0].1.term,
+        dict.search_deinflected("食べなかった")[0].1.term
+    );
+    let senses = mapped.resolve_xref(&XRef::from("今日・こんにち・2"));
+    assert_eq!(
+        senses[0].1.gloss().next().unwrap().content(),
+        Some("these days")
+    );
+
+    for (entry, parsed) in mapped.entries().zip(&dict.entries) {
+        let forms: Vec<_> = entry
+            .forms()
+            .iter()
+            .map(|f| {
+                let kanji = f.kanji.map(|k| k.text().to_owned());
+                (kanji, f.reading.text().to_owned(), f.senses.len())
+            })
+            .collect();
+        let expected: Vec<_> = parsed
+            .forms()
+            .iter()
+            .map(|f| {
+                let kanji = f.kanji.map(|k| k.text.clone());
+                (kanji, f.reading.text.clone(), f.senses.len())
+            })
+            .collect();
+        assert_eq!(forms, expected);
+    }
+    let kanji: Vec<_> = taberu.kanji().map(|k| k.is_search_only()).collect();
+    assert_eq!(kanji, vec![false, true]);
+    let coffee = mapped.find_seq(1044840).unwrap();
+    let irregular: Vec<_> = coffee.reading().map(|r| r.is_irregular()).collect();
+    assert_eq!(irregular, vec![false, true]);
+    assert_eq!(
+        reading.romanize(&crate::romaji::RomanizeOptions::default()),
+        "taberu"
+    );
+
+    assert!(matches!(
+        MappedJMDict::open(
+            &path,
+            &Source {
+                len: 1,
+                ..source.clone()
+            }
+        ),
+        Err(CacheError::SourceChanged)
+    ));
+    let mut bytes = fs::read(&path).unwrap();
+    let changed = Source {
+        version: Some("2020-06-02".to_owned()),
+        ..source.clone()
+    };
+    assert!(matches!(
+        MappedJMDict::from_bytes(bytes.clone(), &changed),
+        Err(CacheError::SourceChanged)
+    ));
+    bytes[4] = 99;
+    assert!(matches!(
+        MappedJMDict::from_bytes(bytes.clone(), &source),
+        Err(CacheError::FormatVersion(99))
+    ));
+
+    // Corrupt references inside the entries read as empty.
+    bytes[4] = crate::mapped::FORMAT_VERSION as u8;
+    let entries_at = u32::from_le_bytes([bytes[32], bytes[33], bytes[34], bytes[35]]) as usize;
+    for b in &mut bytes[entries_at + 4..entries_at + 12] {
+        *b = 0xff;
+    }
+    let corrupt = MappedJMDict::from_bytes(bytes, &source).unwrap();
+    assert_eq!(corrupt.entries().next().unwrap().kanji().len(), 0);
+
+    fs::remove_file(&path).unwrap();
+}
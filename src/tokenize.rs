@@ -0,0 +1,128 @@
+//! Splitting text into words by the cheapest path through a lattice of
+//! dictionary matches.
+//!
+//! Every position of the text is a node, and every form of an entry that
+//! [`IndexedJMDict::scan`] would find there, as written or inflected, is an
+//! edge to the position after it, including the shorter forms of an entry
+//! that `scan` leaves out. Each edge costs a fixed amount, so that fewer
+//! and longer words are preferred, plus penalties for rare entries and for
+//! single kana that are not particles. Characters no entry covers become
+//! unknown tokens, grouping runs of katakana, Latin letters and digits.
+
+use crate::entities::PartOfSpeech;
+use crate::index::{IndexedJMDict, ScanMatch};
+use crate::kana;
+use std::ops::Range;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Token {
+    pub surface: String,
+    // Byte range of the token in the tokenized text.
+    pub span: Range<usize>,
+    // Sequence number of the chosen entry, or `None` for unknown text.
+    pub seq: Option<u32>,
+}
+
+const WORD_COST: u32 = 100;
+const UNKNOWN_COST: u32 = 400;
+
+impl IndexedJMDict {
+    pub fn tokenize(&self, text: &str) -> Vec<Token> {
+        // best[i]: cheapest cost of reaching byte i, with the start of the
+        // last edge and its entry.
+        let mut best: Vec<Option<(u32, usize, Option<u32>)>> = vec![None; text.len() + 1];
+        best[0] = Some((0, 0, None));
+
+        for (start, c) in text.char_indices() {
+            let cost = match best[start] {
+                Some((cost, ..)) => cost,
+                None => continue,
+            };
+            let mut relax = |end: usize, edge: u32, seq: Option<u32>| {
+                let total = cost + edge;
+                if best[end].is_none_or(|(c, ..)| total < c) {
+                    best[end] = Some((total, start, seq));
+                }
+            };
+
+            for m in self.scan_all(text, start) {
+                let surface = &text[start..start + m.len];
+                relax(
+                    start + m.len,
+                    WORD_COST + penalty(&m, surface),
+                    Some(m.entry.seq),
+                );
+            }
+            relax(start + c.len_utf8(), UNKNOWN_COST, None);
+            if let Some(len) = unknown_run(&text[start..]) {
+                relax(start + len, UNKNOWN_COST, None);
+            }
+        }
+
+        let mut tokens = Vec::new();
+        let mut end = text.len();
+        while end > 0 {
+            let (_, start, seq) = best[end].expect("every position is reachable");
+            let surface = &text[start..end];
+            if !surface.trim().is_empty() {
+                tokens.push(Token {
+                    surface: surface.to_owned(),
+                    span: start..end,
+                    seq,
+                });
+            }
+            end = start;
+        }
+
+        tokens.reverse();
+        tokens
+    }
+}
+
+fn penalty(m: &ScanMatch, surface: &str) -> u32 {
+    let entry = m.entry;
+    let mut penalty = match entry.frequency_rank() {
+        Some(rank) => rank.min(48) / 2,
+        None => 30,
+    };
+    if !entry.is_common() {
+        penalty += 20;
+    }
+    if !m.deinflection.reasons.is_empty() {
+        penalty += 10;
+    }
+
+    // Single kana are almost always particles or parts of longer words.
+    let single_kana = surface.chars().count() == 1 && kana::is_all_kana(surface);
+    let is_particle = entry.sense.iter().flat_map(|s| &s.pos).any(|p| {
+        matches!(
+            p,
+            PartOfSpeech::Particle | PartOfSpeech::Copula | PartOfSpeech::AuxiliaryVerb
+        )
+    });
+    if single_kana && !is_particle {
+        penalty += 80;
+    }
+    penalty
+}
+
+// Length in bytes of a run of two or more katakana, Latin letters or
+// digits at the start of `text`, which is likely to be a single word.
+fn unknown_run(text: &str) -> Option<usize> {
+    let class = |c: char| {
+        if kana::is_katakana(c) {
+            1
+        } else if c.is_alphanumeric() && c.is_ascii() {
+            2
+        } else {
+            0
+        }
+    };
+
+    let first = text.chars().next()?;
+    let run = || text.chars().take_while(|&c| class(c) == class(first));
+    if class(first) == 0 || run().count() < 2 {
+        return None;
+    }
+    Some(run().map(char::len_utf8).sum())
+}
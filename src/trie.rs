@@ -0,0 +1,75 @@
+//! Character trie mapping form texts to entry indices.
+
+#[derive(Debug, Default)]
+struct Node {
+    // Sorted by character.
+    children: Vec<(char, usize)>,
+    values: Vec<usize>,
+}
+
+#[derive(Debug)]
+pub struct Trie {
+    nodes: Vec<Node>,
+}
+
+impl Default for Trie {
+    fn default() -> Self {
+        Trie::new()
+    }
+}
+
+impl Trie {
+    pub fn new() -> Self {
+        Trie {
+            nodes: vec![Node::default()],
+        }
+    }
+
+    pub fn insert(&mut self, key: &str, value: usize) {
+        let mut node = 0;
+        for c in key.chars() {
+            node = match self.nodes[node]
+                .children
+                .binary_search_by_key(&c, |&(c, _)| c)
+            {
+                Ok(i) => self.nodes[node].children[i].1,
+                Err(i) => {
+                    let child = self.nodes.len();
+                    self.nodes.push(Node::default());
+                    self.nodes[node].children.insert(i, (c, child));
+                    child
+                }
+            };
+        }
+
+        let values = &mut self.nodes[node].values;
+        if !values.contains(&value) {
+            values.push(value);
+        }
+    }
+
+    /// Keys that are prefixes of `text`, as their length in bytes and their
+    /// values, shortest first. Also returns the number of characters of
+    /// `text` that match some key's path, whether or not a key ends there.
+    pub fn prefixes(&self, text: &str) -> (Vec<(usize, &[usize])>, usize) {
+        let mut found = Vec::new();
+        let mut node = 0;
+        let mut depth = 0;
+
+        for (i, c) in text.char_indices() {
+            let children = &self.nodes[node].children;
+            node = match children.binary_search_by_key(&c, |&(c, _)| c) {
+                Ok(j) => children[j].1,
+                Err(_) => break,
+            };
+            depth += 1;
+
+            let values = &self.nodes[node].values;
+            if !values.is_empty() {
+                found.push((i + c.len_utf8(), values.as_slice()));
+            }
+        }
+
+        (found, depth)
+    }
+}
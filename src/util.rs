@@ -1,8 +1,6 @@
 use crate::errors::{ParseError, XmlError};
 use roxmltree::{ExpandedName, Node, NS_XML_URI};
 use std::borrow::Cow;
-use std::fs;
-use std::io;
 
 macro_rules! const_strs {
     ( $( $id:ident : $val:expr ),* $(,)? ) => {
@@ -30,10 +28,6 @@ pub fn get_node_text<'a>(n: Node<'a, 'a>) -> Result<Cow<'a, str>, ParseError> {
         .map(|t| t.into())
 }
 
-pub fn ns_xml_attr(attr: &str) -> ExpandedName {
+pub fn ns_xml_attr(attr: &str) -> ExpandedName<'_> {
     (NS_XML_URI, attr).into()
 }
-
-pub fn read_file(filepath: &str) -> Result<String, io::Error> {
-    fs::read_to_string(filepath)
-}